@@ -0,0 +1,168 @@
+//! `webgal-asset://` 自定义协议，与 HTTP 文件服务器共用 [`serve_asset`]
+//!
+//! 协议的响应体只能一次性返回：带 Range 的请求每段最多 [`MAX_RANGE_LEN`] 字节，
+//! 不带 Range 的 GET 会把整个文件读入内存，大文件应让 webview 以 Range 请求加载或改用 HTTP 传输
+
+use crate::file_server::asset::{serve_asset, AssetBody, AssetRequest, AssetResponse};
+use crate::file_server::FileServerState;
+use tauri::http;
+use tauri::{Manager, Runtime, UriSchemeContext, UriSchemeResponder};
+
+/// 单个 Range 区间最多返回的字节数，与 Tauri 自带的 asset 协议一致
+const MAX_RANGE_LEN: u64 = 1000 * 1024;
+
+/// `webgal-asset://` 协议处理函数
+///
+/// 与 HTTP 文件服务器共用路径解析、MIME、Range 和缓存逻辑；
+/// 读取文件在阻塞线程池中进行，不会卡住 webview 的主线程
+pub fn handle_asset_protocol<R: Runtime>(
+    ctx: UriSchemeContext<'_, R>,
+    request: http::Request<Vec<u8>>,
+    responder: UriSchemeResponder,
+) {
+    let app = ctx.app_handle().clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = match app.state::<FileServerState>().protocol_config() {
+            Some(config) => serve_asset(&to_asset_request(&request), &config),
+            None => AssetResponse::text(503, "Asset protocol not configured"),
+        };
+        responder.respond(into_http_response(response));
+    });
+}
+
+fn to_asset_request(request: &http::Request<Vec<u8>>) -> AssetRequest {
+    let uri = request.uri();
+    let url = match uri.query() {
+        Some(query) => format!("{}?{}", uri.path(), query),
+        None => uri.path().to_string(),
+    };
+
+    AssetRequest {
+        method: request.method().as_str().to_uppercase(),
+        url,
+        headers: request
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = value.to_str().ok()?;
+                let value = if *name == http::header::RANGE {
+                    cap_range(value)
+                } else {
+                    value.to_string()
+                };
+                Some((name.as_str().to_string(), value))
+            })
+            .collect(),
+    }
+}
+
+/// 把 Range 中的每个区间截短到 [`MAX_RANGE_LEN`]，开放区间 `bytes=100-` 同样只返回一段
+///
+/// 响应体需要整体读入内存，截短后 webview 会按 206 的 Content-Range 继续请求后面的数据；
+/// 无法解析的区间原样保留，交给 `serve_asset` 处理
+fn cap_range(value: &str) -> String {
+    let Some(specs) = value.trim().strip_prefix("bytes=") else {
+        return value.to_string();
+    };
+    let specs: Vec<String> = specs
+        .split(',')
+        .map(|spec| {
+            let spec = spec.trim();
+            let Some((start, end)) = spec.split_once('-') else {
+                return spec.to_string();
+            };
+            let (start, end) = (start.trim(), end.trim());
+            if start.is_empty() {
+                return match end.parse::<u64>() {
+                    Ok(suffix) => format!("-{}", suffix.min(MAX_RANGE_LEN)),
+                    Err(_) => spec.to_string(),
+                };
+            }
+            let Ok(start) = start.parse::<u64>() else {
+                return spec.to_string();
+            };
+            let last = start.saturating_add(MAX_RANGE_LEN - 1);
+            match end.parse::<u64>() {
+                Ok(end) => format!("{}-{}", start, end.min(last)),
+                Err(_) if end.is_empty() => format!("{}-{}", start, last),
+                Err(_) => spec.to_string(),
+            }
+        })
+        .collect();
+    format!("bytes={}", specs.join(","))
+}
+
+/// 自定义协议只能一次性返回完整响应体，因此这里会把流读入内存；
+/// 视频等大文件由 webview 以 Range 请求分段加载，每段都被 [`cap_range`] 限制了长度。
+/// 事件流这类长连接无法通过协议发送，应改为监听 Tauri 事件
+fn into_http_response(response: AssetResponse) -> http::Response<Vec<u8>> {
+    if let AssetBody::Live(_) = response.body {
+        return http::Response::builder()
+            .status(501)
+            .body(b"Event streams require the HTTP transport".to_vec())
+            .unwrap_or_default();
+    }
+
+    let mut builder = http::Response::builder().status(response.status);
+    for (field, value) in &response.headers {
+        builder = builder.header(field.as_str(), value.as_str());
+    }
+
+    let body = match response.body.into_bytes() {
+        Ok(body) => body,
+        Err(e) => {
+            log::error!("读取文件失败: {}", e);
+            return http::Response::builder()
+                .status(500)
+                .body(format!("Internal Server Error: {}", e).into_bytes())
+                .unwrap_or_default();
+        }
+    };
+
+    builder.body(body).unwrap_or_else(|e| {
+        log::error!("构造响应失败: {}", e);
+        http::Response::builder()
+            .status(500)
+            .body(Vec::new())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_range_is_capped() {
+        assert_eq!(cap_range("bytes=0-"), "bytes=0-1023999");
+        assert_eq!(cap_range("bytes=100-"), "bytes=100-1024099");
+    }
+
+    #[test]
+    fn explicit_range_above_cap_is_shortened() {
+        assert_eq!(cap_range("bytes=0-5000000"), "bytes=0-1023999");
+        assert_eq!(cap_range("bytes=10-20"), "bytes=10-20");
+    }
+
+    #[test]
+    fn suffix_range_is_capped() {
+        assert_eq!(cap_range("bytes=-5000000"), "bytes=-1024000");
+        assert_eq!(cap_range("bytes=-500"), "bytes=-500");
+    }
+
+    #[test]
+    fn every_range_in_a_list_is_capped() {
+        assert_eq!(
+            cap_range("bytes=0-99, 2000000-, -3000000"),
+            "bytes=0-99,2000000-3023999,-1024000"
+        );
+    }
+
+    #[test]
+    fn malformed_range_is_left_to_serve_asset() {
+        assert_eq!(cap_range("items=0-5"), "items=0-5");
+        assert_eq!(cap_range("bytes=abc-"), "bytes=abc-");
+        assert_eq!(cap_range("bytes=5"), "bytes=5");
+        assert_eq!(cap_range("bytes=0-xyz"), "bytes=0-xyz");
+    }
+}
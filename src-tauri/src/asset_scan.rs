@@ -0,0 +1,731 @@
+mod index;
+pub mod live2d;
+pub mod motion;
+pub mod validate;
+pub mod watch;
+
+use crate::file_server::archive::AssetFs;
+use image::ImageReader;
+use index::ScanIndex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+
+/// 扫描进度的 Tauri 事件名
+pub const SCAN_PROGRESS_EVENT: &str = "directory-scan-progress";
+
+/// 扫描被取消时返回的错误
+pub const SCAN_CANCELLED: &str = "扫描已取消";
+
+/// 并行解析文件的最大线程数，过多的线程只会争抢磁盘
+const MAX_SCAN_THREADS: usize = 8;
+
+/// 每解析这么多个文件报告一次进度
+const PROGRESS_INTERVAL: usize = 64;
+
+/// 扫描得到的素材类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssetKind {
+    Image,
+    Gif,
+    Webm,
+    /// Cubism 2 的 `model.json`
+    Live2dJson,
+    /// Cubism 3/4 的 `model3.json`
+    Cubism3,
+    /// JSONL 聚合模型
+    Jsonl,
+    /// WebGAL Mano 分层立绘
+    Mano,
+}
+
+impl AssetKind {
+    /// 普通图片和视频按扩展名判断，模型文件需要解析内容
+    fn from_media_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "png" | "jpg" | "jpeg" | "bmp" | "webp" => Some(Self::Image),
+            "gif" => Some(Self::Gif),
+            "webm" => Some(Self::Webm),
+            _ => None,
+        }
+    }
+
+    fn is_model(self) -> bool {
+        matches!(
+            self,
+            Self::Live2dJson | Self::Cubism3 | Self::Jsonl | Self::Mano
+        )
+    }
+}
+
+/// 带元数据的扫描结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetEntry {
+    /// 相对于扫描目录的路径，使用 `/` 分隔
+    pub path: String,
+    pub kind: AssetKind,
+    pub size: u64,
+    /// 修改时间（Unix 毫秒）
+    pub modified: Option<u64>,
+    /// 图片的像素尺寸，无法读取时为空
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// 被归入该条目、不再单独列出的文件（如贴图、物理和子模型），只包含实际存在的文件
+    pub dependencies: Vec<String>,
+    /// 模型引用的文件，保留清单中的结构，包含不存在的文件
+    pub dependency_tree: Vec<ModelReference>,
+}
+
+/// 模型引用的文件的用途
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceKind {
+    Moc,
+    Texture,
+    Physics,
+    Pose,
+    DisplayInfo,
+    UserData,
+    Motion,
+    /// 动作附带的音频
+    Sound,
+    Expression,
+    /// JSONL 中的子模型
+    SubModel,
+    /// Mano 的图层
+    Layer,
+}
+
+/// 模型依赖树中的一个文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelReference {
+    pub kind: ReferenceKind,
+    /// 相对于扫描目录的路径
+    pub path: String,
+    /// 动作所在的分组，或表情的名称
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 动作在分组中的序号，或表情在列表中的序号
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    /// 子模型引用的文件
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<ModelReference>,
+}
+
+impl ModelReference {
+    fn leaf(kind: ReferenceKind, path: String) -> Self {
+        Self {
+            kind,
+            path,
+            name: None,
+            index: None,
+            children: Vec::new(),
+        }
+    }
+
+    /// 深度优先列出自身及所有子引用的路径
+    fn collect_paths<'a>(references: &'a [Self], paths: &mut Vec<&'a String>) {
+        for reference in references {
+            paths.push(&reference.path);
+            Self::collect_paths(&reference.children, paths);
+        }
+    }
+}
+
+/// 扫描进度，作为 [`SCAN_PROGRESS_EVENT`] 事件的数据
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    pub scan_id: Option<String>,
+    /// 需要解析的文件总数，目录遍历完成前为 0
+    pub files_total: usize,
+    pub files_visited: usize,
+    /// 已解析出的模型文件数（Live2D、JSONL 和 Mano）
+    pub models_found: usize,
+    pub done: bool,
+}
+
+type ProgressCallback = Box<dyn Fn(&ScanProgress) + Send + Sync>;
+
+/// 一次扫描的取消标志、进度计数、进度回调和索引位置
+pub struct ScanControl {
+    scan_id: Option<String>,
+    cancelled: Arc<AtomicBool>,
+    on_progress: ProgressCallback,
+    /// 保存扫描索引的目录，为空时每次都完整解析
+    index_dir: Option<PathBuf>,
+    files_total: AtomicUsize,
+    files_visited: AtomicUsize,
+    models_found: AtomicUsize,
+}
+
+impl Default for ScanControl {
+    fn default() -> Self {
+        Self::new(None, Arc::default(), |_| {})
+    }
+}
+
+impl ScanControl {
+    pub fn new(
+        scan_id: Option<String>,
+        cancelled: Arc<AtomicBool>,
+        on_progress: impl Fn(&ScanProgress) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            scan_id,
+            cancelled,
+            on_progress: Box::new(on_progress),
+            index_dir: None,
+            files_total: AtomicUsize::new(0),
+            files_visited: AtomicUsize::new(0),
+            models_found: AtomicUsize::new(0),
+        }
+    }
+
+    /// 在 `index_dir` 中保存每个目录的解析结果，再次扫描时只解析变化过的文件
+    pub fn with_index_dir(mut self, index_dir: PathBuf) -> Self {
+        self.index_dir = Some(index_dir);
+        self
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(SCAN_CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn set_total(&self, total: usize) {
+        self.files_total.store(total, Ordering::Relaxed);
+        self.report(false);
+    }
+
+    fn visited(&self, analysis: &FileAnalysis) {
+        if analysis.kind.is_some_and(AssetKind::is_model) {
+            self.models_found.fetch_add(1, Ordering::Relaxed);
+        }
+        let visited = self.files_visited.fetch_add(1, Ordering::Relaxed) + 1;
+        if visited.is_multiple_of(PROGRESS_INTERVAL) {
+            self.report(false);
+        }
+    }
+
+    fn report(&self, done: bool) {
+        (self.on_progress)(&ScanProgress {
+            scan_id: self.scan_id.clone(),
+            files_total: self.files_total.load(Ordering::Relaxed),
+            files_visited: self.files_visited.load(Ordering::Relaxed),
+            models_found: self.models_found.load(Ordering::Relaxed),
+            done,
+        });
+    }
+}
+
+/// 正在进行的扫描，按前端传入的扫描 ID 取消
+#[derive(Default)]
+pub struct ScanRegistry {
+    scans: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl ScanRegistry {
+    /// 登记新的扫描并返回其取消标志，同 ID 的旧扫描会被取消
+    pub fn begin(&self, scan_id: &str) -> Arc<AtomicBool> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        if let Ok(mut scans) = self.scans.lock() {
+            if let Some(previous) = scans.insert(scan_id.to_string(), Arc::clone(&cancelled)) {
+                previous.store(true, Ordering::Relaxed);
+            }
+        }
+        cancelled
+    }
+
+    /// 扫描结束后移除登记；已被同 ID 的新扫描替换时保留新的登记
+    pub fn finish(&self, scan_id: &str, cancelled: &Arc<AtomicBool>) {
+        if let Ok(mut scans) = self.scans.lock() {
+            if scans
+                .get(scan_id)
+                .is_some_and(|current| Arc::ptr_eq(current, cancelled))
+            {
+                scans.remove(scan_id);
+            }
+        }
+    }
+
+    /// 取消扫描，返回该扫描是否仍在进行
+    pub fn cancel(&self, scan_id: &str) -> bool {
+        let cancelled = self
+            .scans
+            .lock()
+            .ok()
+            .and_then(|mut scans| scans.remove(scan_id));
+        match cancelled {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// 单个文件的解析结果，只取决于文件本身及 `inputs` 中的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileAnalysis {
+    kind: Option<AssetKind>,
+    /// 引用的文件
+    references: Vec<ModelReference>,
+    /// 解析时读取过的其他文件（如 JSONL 的子模型），它们变化时需要重新解析
+    inputs: Vec<PathBuf>,
+}
+
+/// 归入规则应用后保留下来的文件
+struct FoldedAsset {
+    path: PathBuf,
+    relative: String,
+    kind: AssetKind,
+    references: Vec<ModelReference>,
+}
+
+/// 扫描目录，返回可选择的素材的相对路径
+///
+/// 模型引用的贴图、子模型等文件会被排除，只保留模型入口文件
+pub fn scan_paths(
+    asset_fs: &AssetFs,
+    base_dir: &Path,
+    control: &ScanControl,
+) -> Result<Vec<String>, String> {
+    let index = ScanIndex::load(control.index_dir.as_deref(), base_dir);
+    let paths = fold(asset_fs, base_dir, control, &index)?
+        .into_iter()
+        .map(|asset| asset.relative)
+        .collect();
+    index.save();
+    Ok(paths)
+}
+
+/// 与 [`scan_paths`] 相同的扫描，同时返回类型、大小、修改时间、图片尺寸和被归入的文件
+pub fn scan_entries(
+    asset_fs: &AssetFs,
+    base_dir: &Path,
+    control: &ScanControl,
+) -> Result<Vec<AssetEntry>, String> {
+    let index = ScanIndex::load(control.index_dir.as_deref(), base_dir);
+    let folded = fold(asset_fs, base_dir, control, &index)?;
+    let entries = parallel_map(folded, control, |asset| {
+        describe(asset_fs, base_dir, asset, &index)
+    })?;
+    index.save();
+    Ok(entries)
+}
+
+/// 并行解析每个文件，再按遍历顺序应用归入规则
+///
+/// 解析只取决于文件本身，可以并行，未变化的文件直接使用索引中的结果；
+/// 归入只对之后遇到的文件生效，必须按遍历顺序进行
+fn fold(
+    asset_fs: &AssetFs,
+    base_dir: &Path,
+    control: &ScanControl,
+    index: &ScanIndex,
+) -> Result<Vec<FoldedAsset>, String> {
+    let mut files = Vec::new();
+    collect_files(asset_fs, base_dir, &mut files, control)?;
+    control.set_total(files.len());
+
+    let analyses = parallel_map(files.clone(), control, |path| {
+        let analysis = match relative_path(&path, base_dir) {
+            Ok(relative) => index.analysis(asset_fs, &path, &relative, || {
+                analyze(asset_fs, &path, base_dir)
+            }),
+            Err(_) => analyze(asset_fs, &path, base_dir),
+        };
+        control.visited(&analysis);
+        analysis
+    })?;
+    control.report(true);
+
+    let mut folded = Vec::new();
+    let mut excluded_files = HashSet::new();
+    for (path, analysis) in files.into_iter().zip(analyses) {
+        let relative = relative_path(&path, base_dir)?;
+        let Some(kind) = analysis.kind else {
+            continue;
+        };
+        let mut references = Vec::new();
+        ModelReference::collect_paths(&analysis.references, &mut references);
+        excluded_files.extend(references.into_iter().cloned());
+
+        // JSONL 总是保留；其他文件只有未被之前的模型引用时才保留
+        if kind == AssetKind::Jsonl || !excluded_files.contains(&relative) {
+            folded.push(FoldedAsset {
+                path,
+                relative,
+                kind,
+                references: analysis.references,
+            });
+        }
+    }
+    Ok(folded)
+}
+
+/// 深度优先收集目录下所有带扩展名的文件
+///
+/// 同一目录中 json/jsonl 排在最前，保证模型先于它引用的贴图被处理
+fn collect_files(
+    asset_fs: &AssetFs,
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+    control: &ScanControl,
+) -> Result<(), String> {
+    control.check_cancelled()?;
+    let mut entries = asset_fs
+        .read_dir(dir)
+        .map_err(|e| format!("读取目录失败: {}", e))?;
+    entries.sort_by_key(|path| match extension(path).as_str() {
+        "jsonl" | "json" => 0,
+        _ => 1,
+    });
+
+    for path in entries {
+        if asset_fs.is_dir(&path) {
+            collect_files(asset_fs, &path, files, control)?;
+        } else if path.extension().is_some() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// 在多个线程上处理 `items`，结果保持原顺序；取消后尚未开始的项目不再处理
+fn parallel_map<T: Send, R: Send>(
+    items: Vec<T>,
+    control: &ScanControl,
+    f: impl Fn(T) -> R + Sync,
+) -> Result<Vec<R>, String> {
+    let len = items.len();
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_SCAN_THREADS)
+        .min(len)
+        .max(1);
+    let queue = Mutex::new(items.into_iter().enumerate());
+
+    let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(len).collect();
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    while !control.is_cancelled() {
+                        let next = queue.lock().ok().and_then(|mut queue| queue.next());
+                        let Some((i, item)) = next else {
+                            break;
+                        };
+                        done.push((i, f(item)));
+                    }
+                    done
+                })
+            })
+            .collect();
+        for handle in handles {
+            for (i, result) in handle.join().unwrap_or_default() {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    control.check_cancelled()?;
+    results
+        .into_iter()
+        .collect::<Option<Vec<R>>>()
+        .ok_or_else(|| "扫描线程异常退出".to_string())
+}
+
+fn analyze(asset_fs: &AssetFs, path: &Path, base_dir: &Path) -> FileAnalysis {
+    match extension(path).as_str() {
+        "jsonl" => analyze_jsonl(asset_fs, path, base_dir),
+        "json" => analyze_json(asset_fs, path, base_dir),
+        ext => FileAnalysis {
+            kind: AssetKind::from_media_extension(ext),
+            references: Vec::new(),
+            inputs: Vec::new(),
+        },
+    }
+}
+
+/// JSONL 每行的 `path` 指向一个子模型，子模型及其引用的所有文件都归入 JSONL
+fn analyze_jsonl(asset_fs: &AssetFs, path: &Path, base_dir: &Path) -> FileAnalysis {
+    let mut analysis = FileAnalysis {
+        kind: Some(AssetKind::Jsonl),
+        references: Vec::new(),
+        inputs: Vec::new(),
+    };
+    let (Some(parent_dir), Ok(content)) = (path.parent(), asset_fs.read_to_string(path)) else {
+        return analysis;
+    };
+
+    for line in content.lines() {
+        let Ok(json) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let Some(path_str) = json.get("path").and_then(|p| p.as_str()) else {
+            continue;
+        };
+        let Some(sub_model_path) = join_reference(parent_dir, path_str) else {
+            continue;
+        };
+        let Ok(relative) = relative_path(&sub_model_path, base_dir) else {
+            continue;
+        };
+        let mut sub_model = ModelReference::leaf(ReferenceKind::SubModel, relative);
+        analysis.inputs.push(sub_model_path.clone());
+
+        // 子模型引用的路径以子模型 json 所在目录为基准
+        let manifest = asset_fs
+            .read_to_string(&sub_model_path)
+            .ok()
+            .and_then(|sub_content| serde_json::from_str::<Value>(&sub_content).ok())
+            .and_then(|sub_json| live2d::resolve(&sub_json));
+        if let Some(manifest) = manifest {
+            let sub_dir = sub_model_path.parent().unwrap_or(parent_dir);
+            sub_model.children = manifest_references(&manifest, sub_dir, base_dir);
+        }
+        analysis.references.push(sub_model);
+    }
+    analysis
+}
+
+/// 判断 JSON 是否为 Live2D 或 Mano 模型，并找出它引用的文件
+///
+/// 无法读取或解析的 JSON 不作为素材
+fn analyze_json(asset_fs: &AssetFs, path: &Path, base_dir: &Path) -> FileAnalysis {
+    let json = asset_fs
+        .read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<Value>(&content).ok());
+    let (Some(json), Some(parent)) = (json, path.parent()) else {
+        return FileAnalysis {
+            kind: None,
+            references: Vec::new(),
+            inputs: Vec::new(),
+        };
+    };
+
+    // 引用路径以 json 文件所在目录为基准
+    let mut references = Vec::new();
+    let manifest = live2d::resolve(&json);
+    if let Some(manifest) = &manifest {
+        references = manifest_references(manifest, parent, base_dir);
+    }
+
+    // Mano：settings（或 setting）, assets, controller 字段
+    let is_mano = json.get("settings").is_some()
+        || json.get("setting").is_some()
+        || json.get("assets").is_some()
+        || json.get("controller").is_some();
+    if is_mano {
+        let layers = json
+            .get("assets")
+            .and_then(|assets| assets.get("layers"))
+            .and_then(|l| l.as_array());
+        let layer_paths = layers
+            .into_iter()
+            .flatten()
+            .filter_map(|layer| layer.get("path").and_then(|p| p.as_str()));
+        references.extend(layer_paths.filter_map(|file| {
+            let relative = relative_path(&join_reference(parent, file)?, base_dir).ok()?;
+            Some(ModelReference::leaf(ReferenceKind::Layer, relative))
+        }));
+    }
+
+    // 字段都不匹配时再根据文件名判断，增加鲁棒性
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let live2d_kind = manifest.map(|manifest| match manifest.generation {
+        live2d::Generation::Cubism2 => AssetKind::Live2dJson,
+        live2d::Generation::Cubism3 => AssetKind::Cubism3,
+    });
+    let kind = match (live2d_kind, is_mano) {
+        (Some(kind), _) => Some(kind),
+        (None, true) => Some(AssetKind::Mano),
+        (None, false) if file_name.contains(".char.json") => Some(AssetKind::Mano),
+        (None, false) if file_name.contains("model.json") => Some(AssetKind::Live2dJson),
+        (None, false) => None,
+    };
+
+    FileAnalysis {
+        kind,
+        references,
+        inputs: Vec::new(),
+    }
+}
+
+/// 把清单中的引用转换为相对于扫描目录的依赖，`manifest_dir` 是清单所在目录
+fn manifest_references(
+    manifest: &live2d::Live2dManifest,
+    manifest_dir: &Path,
+    base_dir: &Path,
+) -> Vec<ModelReference> {
+    manifest
+        .references
+        .iter()
+        .filter_map(|reference| {
+            let path = join_reference(manifest_dir, &reference.file)?;
+            let relative = relative_path(&path, base_dir).ok()?;
+            Some(ModelReference {
+                name: reference.name.clone(),
+                index: reference.index,
+                ..ModelReference::leaf(reference.kind, relative)
+            })
+        })
+        .collect()
+}
+
+/// 读取大小、修改时间和图片尺寸，并筛选出实际存在的被归入文件
+fn describe(
+    asset_fs: &AssetFs,
+    base_dir: &Path,
+    asset: FoldedAsset,
+    index: &ScanIndex,
+) -> AssetEntry {
+    let (size, modified) = asset_fs.stat(&asset.path).unwrap_or((0, None));
+    let (width, height) = match asset.kind {
+        AssetKind::Image | AssetKind::Gif => index
+            .dimensions(&asset.relative, || image_dimensions(asset_fs, &asset.path))
+            .map_or((None, None), |(width, height)| (Some(width), Some(height))),
+        _ => (None, None),
+    };
+
+    let mut paths = Vec::new();
+    ModelReference::collect_paths(&asset.references, &mut paths);
+    let mut seen = HashSet::new();
+    let dependencies = paths
+        .into_iter()
+        .filter(|dependency| seen.insert(*dependency))
+        .filter(|dependency| asset_fs.is_file(&base_dir.join(dependency)))
+        .cloned()
+        .collect();
+
+    AssetEntry {
+        path: asset.relative,
+        kind: asset.kind,
+        size,
+        modified: modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_millis() as u64),
+        width,
+        height,
+        dependencies,
+        dependency_tree: asset.references,
+    }
+}
+
+/// 只解码文件头读取图片尺寸；zip 内的图片需要先读入内存
+fn image_dimensions(asset_fs: &AssetFs, path: &Path) -> Option<(u32, u32)> {
+    match asset_fs {
+        AssetFs::Disk => image::image_dimensions(path).ok(),
+        AssetFs::Archive(_) => ImageReader::new(Cursor::new(asset_fs.read(path).ok()?))
+            .with_guessed_format()
+            .ok()?
+            .into_dimensions()
+            .ok(),
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn relative_path(path: &Path, base_dir: &Path) -> Result<String, String> {
+    path.strip_prefix(base_dir)
+        .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+        .map_err(|e| format!("计算相对路径失败: {}", e))
+}
+
+/// 把模型文件中的引用接到 `dir` 后，按字面折叠 `.` 和 `..`，
+/// 使 `../motions/idle.mtn` 这样的引用得到正确的相对路径和 zip 条目名称
+fn join_reference(dir: &Path, file: &str) -> Option<PathBuf> {
+    walk_reference(dir, file, |_, part| Some(part.to_string()))
+}
+
+/// 逐级解析引用：`push` 根据当前目录决定下一级实际使用的名称，返回 `None` 时引用无效；
+/// 绝对路径不会出现在模型文件中，同样视为无效
+fn walk_reference(
+    dir: &Path,
+    file: &str,
+    mut push: impl FnMut(&Path, &str) -> Option<String>,
+) -> Option<PathBuf> {
+    let mut current = dir.to_path_buf();
+    for component in Path::new(&file.replace('\\', "/")).components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                current.pop();
+            }
+            Component::Normal(part) => {
+                let part = push(&current, &part.to_string_lossy())?;
+                current.push(part);
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+    Some(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn join_reference_folds_parent_components() {
+        let dir = Path::new("game/figure/model");
+        assert_eq!(
+            join_reference(dir, "../motions/./idle.mtn"),
+            Some(PathBuf::from("game/figure/motions/idle.mtn"))
+        );
+        assert_eq!(
+            join_reference(dir, "textures\\texture_00.png"),
+            Some(PathBuf::from("game/figure/model/textures/texture_00.png"))
+        );
+        assert_eq!(join_reference(dir, "/etc/passwd"), None);
+    }
+
+    #[test]
+    fn manifest_references_resolve_parent_references() {
+        let json = json!({
+            "Version": 3,
+            "FileReferences": {
+                "Moc": "model.moc3",
+                "Motions": {
+                    "Idle": [{ "File": "../motions/idle.motion3.json" }]
+                }
+            }
+        });
+        let manifest = live2d::resolve(&json).expect("Cubism 3 模型");
+        let base_dir = Path::new("/game/figure");
+        let references = manifest_references(&manifest, &base_dir.join("model"), base_dir);
+        let files: Vec<&str> = references.iter().map(|r| r.path.as_str()).collect();
+        assert!(files.contains(&"model/model.moc3"));
+        assert!(files.contains(&"motions/idle.motion3.json"));
+    }
+}
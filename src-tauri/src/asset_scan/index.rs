@@ -0,0 +1,199 @@
+use super::FileAnalysis;
+use crate::file_server::archive::AssetFs;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+/// 索引格式版本；解析规则变化时递增，旧版本的索引会被整体丢弃
+const INDEX_VERSION: u32 = 2;
+
+/// 文件大小和修改时间，两者都不变时认为内容没有变化
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Stamp {
+    size: u64,
+    /// 修改时间（Unix 纳秒）
+    modified: Option<u64>,
+}
+
+impl Stamp {
+    fn of(asset_fs: &AssetFs, path: &Path) -> Option<Self> {
+        let (size, modified) = asset_fs.stat(path).ok()?;
+        Some(Self {
+            size,
+            modified: modified
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map(|duration| duration.as_nanos() as u64),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedFile {
+    stamp: Stamp,
+    analysis: FileAnalysis,
+    /// `analysis.inputs` 中每个文件在解析时的指纹，文件不存在时为空
+    input_stamps: Vec<Option<Stamp>>,
+    /// 图片尺寸，只在详细扫描中读取
+    dimensions: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    /// 扫描的目录，用于排除文件名哈希冲突
+    dir: String,
+    files: HashMap<String, IndexedFile>,
+}
+
+/// 一次扫描使用的索引：按相对路径查找上次的解析结果，并记录本次的结果
+///
+/// 文件的大小、修改时间以及解析时读取过的其他文件都没有变化时才复用结果，
+/// 因此增量扫描与完整扫描的结果一致
+pub(super) struct ScanIndex {
+    /// 索引文件位置，为空时不使用索引
+    file: Option<PathBuf>,
+    dir: String,
+    previous: HashMap<String, IndexedFile>,
+    next: Mutex<HashMap<String, IndexedFile>>,
+}
+
+impl ScanIndex {
+    /// 读取 `index_dir` 中 `base_dir` 对应的索引，不存在或已过期时从空索引开始
+    pub fn load(index_dir: Option<&Path>, base_dir: &Path) -> Self {
+        let dir = base_dir.to_string_lossy().to_string();
+        let file = index_dir.map(|index_dir| index_dir.join(index_file_name(&dir)));
+
+        let previous = file
+            .as_ref()
+            .and_then(|file| fs::read(file).ok())
+            .and_then(|data| serde_json::from_slice::<IndexFile>(&data).ok())
+            .filter(|index| index.version == INDEX_VERSION && index.dir == dir)
+            .map(|index| index.files)
+            .unwrap_or_default();
+
+        Self {
+            file,
+            dir,
+            previous,
+            next: Mutex::default(),
+        }
+    }
+
+    /// 返回文件的解析结果，索引中的结果仍然有效时直接使用，否则调用 `analyze` 重新解析
+    pub fn analysis(
+        &self,
+        asset_fs: &AssetFs,
+        path: &Path,
+        relative: &str,
+        analyze: impl FnOnce() -> FileAnalysis,
+    ) -> FileAnalysis {
+        if self.file.is_none() {
+            return analyze();
+        }
+        let Some(stamp) = Stamp::of(asset_fs, path) else {
+            return analyze();
+        };
+
+        let cached = self.previous.get(relative).filter(|indexed| {
+            indexed.stamp == stamp
+                && indexed.analysis.inputs.len() == indexed.input_stamps.len()
+                && indexed
+                    .analysis
+                    .inputs
+                    .iter()
+                    .zip(&indexed.input_stamps)
+                    .all(|(input, input_stamp)| Stamp::of(asset_fs, input) == *input_stamp)
+        });
+        let indexed = match cached {
+            Some(indexed) => indexed.clone(),
+            None => {
+                let analysis = analyze();
+                let input_stamps = analysis
+                    .inputs
+                    .iter()
+                    .map(|input| Stamp::of(asset_fs, input))
+                    .collect();
+                IndexedFile {
+                    stamp,
+                    analysis,
+                    input_stamps,
+                    dimensions: None,
+                }
+            }
+        };
+
+        let analysis = indexed.analysis.clone();
+        if let Ok(mut next) = self.next.lock() {
+            next.insert(relative.to_string(), indexed);
+        }
+        analysis
+    }
+
+    /// 返回图片尺寸，索引中没有时调用 `read` 读取并记录
+    ///
+    /// 必须在同一次扫描的 [`ScanIndex::analysis`] 之后调用，此时文件指纹已经核对过
+    pub fn dimensions(
+        &self,
+        relative: &str,
+        read: impl FnOnce() -> Option<(u32, u32)>,
+    ) -> Option<(u32, u32)> {
+        let cached = self
+            .next
+            .lock()
+            .ok()
+            .and_then(|next| next.get(relative).and_then(|indexed| indexed.dimensions));
+        if cached.is_some() {
+            return cached;
+        }
+
+        let dimensions = read();
+        if let Ok(mut next) = self.next.lock() {
+            if let Some(indexed) = next.get_mut(relative) {
+                indexed.dimensions = dimensions;
+            }
+        }
+        dimensions
+    }
+
+    /// 把本次扫描的结果写回磁盘；已删除的文件不会再出现在索引中
+    pub fn save(self) {
+        let Some(file) = self.file else {
+            return;
+        };
+        let index = IndexFile {
+            version: INDEX_VERSION,
+            dir: self.dir,
+            files: self.next.into_inner().unwrap_or_default(),
+        };
+
+        // 先写临时文件再重命名，同一目录的两次扫描同时结束时不会写出半个文件
+        let write_result = serde_json::to_vec(&index)
+            .map_err(std::io::Error::from)
+            .and_then(|data| {
+                if let Some(parent) = file.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let temp = file.with_extension(format!("{:08x}.tmp", rand::random::<u32>()));
+                fs::write(&temp, data)?;
+                fs::rename(&temp, &file).inspect_err(|_| {
+                    let _ = fs::remove_file(&temp);
+                })
+            });
+        if let Err(e) = write_result {
+            log::warn!("写入素材索引失败 {:?}: {}", file, e);
+        }
+    }
+}
+
+/// 索引文件名由扫描目录决定
+fn index_file_name(dir: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    dir.hash(&mut hasher);
+    format!("{:016x}.json", hasher.finish())
+}
@@ -0,0 +1,371 @@
+use super::{join_reference, ReferenceKind};
+use crate::file_server::archive::AssetFs;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+
+/// Live2D 模型清单的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Generation {
+    /// Cubism 2 的 `model.json`
+    Cubism2,
+    /// Cubism 3/4 的 `model3.json`
+    Cubism3,
+}
+
+/// 清单中的一条文件引用，`file` 是清单中写的路径，以清单所在目录为基准
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestReference {
+    pub kind: ReferenceKind,
+    pub file: String,
+    /// 动作所在的分组，或表情的名称
+    pub name: Option<String>,
+    /// 动作在分组中的序号，或表情在列表中的序号
+    pub index: Option<usize>,
+}
+
+/// 解析后的 Live2D 模型清单
+#[derive(Debug, Clone)]
+pub struct Live2dManifest {
+    pub generation: Generation,
+    /// 按清单中出现的顺序排列；动作的音频紧跟在动作之后
+    pub references: Vec<ManifestReference>,
+    /// 没有写文件路径、永远无法播放的动作和表情，`file` 为空
+    pub entries_without_file: Vec<ManifestReference>,
+}
+
+/// 一个动作分组，用于 `changeFigure` 的 `-motion` 参数
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionGroup {
+    pub name: String,
+    pub motions: Vec<MotionEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionEntry {
+    /// 在分组中的序号
+    pub index: usize,
+    pub file: String,
+}
+
+impl Live2dManifest {
+    /// 按清单中出现的顺序列出动作分组及其中的动作
+    pub fn motion_groups(&self) -> Vec<MotionGroup> {
+        let mut groups: Vec<MotionGroup> = Vec::new();
+        let motions = self
+            .references
+            .iter()
+            .filter(|reference| reference.kind == ReferenceKind::Motion);
+        for motion in motions {
+            let name = motion.name.clone().unwrap_or_default();
+            let entry = MotionEntry {
+                index: motion.index.unwrap_or_default(),
+                file: motion.file.clone(),
+            };
+            match groups.iter_mut().find(|group| group.name == name) {
+                Some(group) => group.motions.push(entry),
+                None => groups.push(MotionGroup {
+                    name,
+                    motions: vec![entry],
+                }),
+            }
+        }
+        groups
+    }
+
+    /// 表情名称；没有名称的表情使用文件路径
+    pub fn expression_names(&self) -> Vec<String> {
+        self.references
+            .iter()
+            .filter(|reference| reference.kind == ReferenceKind::Expression)
+            .map(|reference| {
+                reference
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| reference.file.clone())
+            })
+            .collect()
+    }
+}
+
+/// JSONL 中的一个子模型
+#[derive(Debug, Clone)]
+pub struct SubModelManifest {
+    /// JSONL 中写的 `path`
+    pub path: String,
+    pub full_path: PathBuf,
+    pub manifest: Live2dManifest,
+}
+
+/// JSONL 中所有写了 `path` 的行，每行是一个图层
+#[derive(Debug, Clone, Default)]
+pub struct JsonlSubModels {
+    pub loaded: Vec<SubModelManifest>,
+    /// 无法读取或不是 Live2D 模型的子模型（JSONL 中写的 `path`）
+    pub failed: Vec<String>,
+}
+
+impl JsonlSubModels {
+    pub fn layer_count(&self) -> usize {
+        self.loaded.len() + self.failed.len()
+    }
+}
+
+/// 读取 JSONL 每行 `path` 指向的子模型，无法读取或不是 Live2D 模型的子模型记录在 `failed` 中
+pub fn jsonl_sub_models(asset_fs: &AssetFs, jsonl_path: &Path) -> JsonlSubModels {
+    let mut sub_models = JsonlSubModels::default();
+    let Ok(content) = asset_fs.read_to_string(jsonl_path) else {
+        return sub_models;
+    };
+    let jsonl_dir = jsonl_path.parent().unwrap_or(Path::new(""));
+
+    let paths = content.lines().filter_map(|line| {
+        let json = serde_json::from_str::<Value>(line).ok()?;
+        Some(json.get("path")?.as_str()?.to_string())
+    });
+    for path in paths {
+        let Some(full_path) = join_reference(jsonl_dir, &path) else {
+            sub_models.failed.push(path);
+            continue;
+        };
+        let manifest = asset_fs
+            .read_to_string(&full_path)
+            .ok()
+            .and_then(|sub_content| serde_json::from_str::<Value>(&sub_content).ok())
+            .and_then(|json| resolve(&json));
+        match manifest {
+            Some(manifest) => sub_models.loaded.push(SubModelManifest {
+                path,
+                full_path,
+                manifest,
+            }),
+            None => sub_models.failed.push(path),
+        }
+    }
+    sub_models
+}
+
+/// 合并后的动作或表情名称，以及提供它的子模型
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameSource {
+    pub name: String,
+    /// 提供该名称的子模型（JSONL 中写的 `path`）
+    pub sub_models: Vec<String>,
+    /// 只有部分子模型提供该名称，播放时其他图层不会跟着动
+    pub partial: bool,
+}
+
+/// 合并 `declared`（JSONL 汇总行中的名称）和每个子模型的名称，去重并保持首次出现的顺序
+///
+/// 无法读取的子模型同样算作图层，因此有子模型读取失败时所有名称都是部分提供的
+pub fn merge_names(
+    declared: &[String],
+    sub_models: &JsonlSubModels,
+    names: impl Fn(&Live2dManifest) -> Vec<String>,
+) -> Vec<NameSource> {
+    let mut merged: Vec<NameSource> = Vec::new();
+    let provided = sub_models.loaded.iter().flat_map(|sub_model| {
+        names(&sub_model.manifest)
+            .into_iter()
+            .map(move |name| (name, Some(&sub_model.path)))
+    });
+    let all_names = declared
+        .iter()
+        .map(|name| (name.clone(), None))
+        .chain(provided);
+
+    for (name, sub_model) in all_names {
+        let index = match merged.iter().position(|source| source.name == name) {
+            Some(index) => index,
+            None => {
+                merged.push(NameSource {
+                    name,
+                    sub_models: Vec::new(),
+                    partial: false,
+                });
+                merged.len() - 1
+            }
+        };
+        let providers = &mut merged[index].sub_models;
+        if let Some(path) = sub_model.filter(|path| !providers.contains(path)) {
+            providers.push(path.clone());
+        }
+    }
+
+    for source in &mut merged {
+        source.partial = source.sub_models.len() < sub_models.layer_count();
+    }
+    merged
+}
+
+/// JSONL 汇总行或旧式模型文件中列出的动作和表情名称：
+/// 字符串数组、带 name/file/id 字段的对象数组，或以名称为键的对象
+pub fn declared_names(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                Value::String(name) => Some(name.clone()),
+                Value::Object(obj) => ["name", "file", "id"]
+                    .iter()
+                    .find_map(|key| obj.get(*key).and_then(|v| v.as_str()))
+                    .map(str::to_string),
+                _ => None,
+            })
+            .collect(),
+        Some(Value::Object(obj)) => obj.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// 判断 JSON 是否为 Live2D 模型清单，并列出它引用的所有文件
+///
+/// - Cubism 2：`model`、`textures`、`physics`、`pose`、`motions` 分组（含 `sound`）和 `expressions`
+/// - Cubism 3/4：`FileReferences` 中的 `Moc`、`Textures`、`Physics`、`Pose`、`DisplayInfo`、
+///   `UserData`、`Motions` 分组（含 `Sound`）和 `Expressions`
+pub fn resolve(json: &Value) -> Option<Live2dManifest> {
+    // Cubism 2：model, textures, motions 字段
+    if json.get("model").is_some()
+        || json.get("textures").is_some()
+        || json.get("motions").is_some()
+    {
+        let mut resolver = Resolver::default();
+        resolver.file(ReferenceKind::Moc, json.get("model"));
+        resolver.files(ReferenceKind::Texture, json.get("textures"));
+        resolver.file(ReferenceKind::Physics, json.get("physics"));
+        resolver.file(ReferenceKind::Pose, json.get("pose"));
+        resolver.motions(json.get("motions"), "file", "sound");
+        resolver.expressions(json.get("expressions"), "name", "file");
+        return Some(Live2dManifest {
+            generation: Generation::Cubism2,
+            references: resolver.references,
+            entries_without_file: resolver.entries_without_file,
+        });
+    }
+
+    // Cubism 3/4：Version 和 FileReferences 字段
+    if json.get("Version").is_some() && json.get("FileReferences").is_some() {
+        let mut resolver = Resolver::default();
+        if let Some(file_refs) = json.get("FileReferences").and_then(|fr| fr.as_object()) {
+            resolver.file(ReferenceKind::Moc, file_refs.get("Moc"));
+            resolver.files(ReferenceKind::Texture, file_refs.get("Textures"));
+            resolver.file(ReferenceKind::Physics, file_refs.get("Physics"));
+            resolver.file(ReferenceKind::Pose, file_refs.get("Pose"));
+            resolver.file(ReferenceKind::DisplayInfo, file_refs.get("DisplayInfo"));
+            resolver.file(ReferenceKind::UserData, file_refs.get("UserData"));
+            resolver.motions(file_refs.get("Motions"), "File", "Sound");
+            resolver.expressions(file_refs.get("Expressions"), "Name", "File");
+        }
+        return Some(Live2dManifest {
+            generation: Generation::Cubism3,
+            references: resolver.references,
+            entries_without_file: resolver.entries_without_file,
+        });
+    }
+
+    None
+}
+
+#[derive(Default)]
+struct Resolver {
+    references: Vec<ManifestReference>,
+    entries_without_file: Vec<ManifestReference>,
+}
+
+impl Resolver {
+    fn push(&mut self, kind: ReferenceKind, file: &str, name: Option<&str>, index: Option<usize>) {
+        let reference = ManifestReference {
+            kind,
+            file: file.to_string(),
+            name: name.map(str::to_string),
+            index,
+        };
+        if !file.is_empty() {
+            self.references.push(reference);
+        } else if matches!(kind, ReferenceKind::Motion | ReferenceKind::Expression) {
+            self.entries_without_file.push(reference);
+        }
+    }
+
+    fn file(&mut self, kind: ReferenceKind, value: Option<&Value>) {
+        if let Some(file) = value.and_then(|v| v.as_str()) {
+            self.push(kind, file, None, None);
+        }
+    }
+
+    fn files(&mut self, kind: ReferenceKind, value: Option<&Value>) {
+        let files = value.and_then(|v| v.as_array());
+        for file in files.into_iter().flatten().filter_map(|f| f.as_str()) {
+            self.push(kind, file, None, None);
+        }
+    }
+
+    /// 动作按分组保存：`{ "分组": [{ "file": ..., "sound": ... }] }`
+    fn motions(&mut self, value: Option<&Value>, file_key: &str, sound_key: &str) {
+        let groups = value.and_then(|v| v.as_object());
+        for (group, motions) in groups.into_iter().flat_map(Map::iter) {
+            let motions = motions.as_array();
+            for (index, motion) in motions.into_iter().flatten().enumerate() {
+                let file = motion.get(file_key).and_then(|f| f.as_str());
+                self.push(
+                    ReferenceKind::Motion,
+                    file.unwrap_or(""),
+                    Some(group),
+                    Some(index),
+                );
+                if let Some(sound) = motion.get(sound_key).and_then(|s| s.as_str()) {
+                    self.push(ReferenceKind::Sound, sound, Some(group), Some(index));
+                }
+            }
+        }
+    }
+
+    /// 表情是数组：`[{ "name": ..., "file": ... }]`
+    fn expressions(&mut self, value: Option<&Value>, name_key: &str, file_key: &str) {
+        let expressions = value.and_then(|v| v.as_array());
+        for (index, expression) in expressions.into_iter().flatten().enumerate() {
+            let file = expression.get(file_key).and_then(|f| f.as_str());
+            let name = expression.get(name_key).and_then(|n| n.as_str());
+            self.push(
+                ReferenceKind::Expression,
+                file.unwrap_or(""),
+                name,
+                Some(index),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn motion_groups_keep_manifest_order() {
+        let json: Value = serde_json::from_str(
+            r#"{
+                "Version": 3,
+                "FileReferences": {
+                    "Moc": "model.moc3",
+                    "Motions": {
+                        "TapBody": [{ "File": "motions/tap.motion3.json" }],
+                        "Idle": [
+                            { "File": "motions/idle_00.motion3.json" },
+                            { "File": "motions/idle_01.motion3.json" }
+                        ]
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        let manifest = resolve(&json).expect("Cubism 3 模型");
+        let groups = manifest.motion_groups();
+        let names: Vec<&str> = groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(names, ["TapBody", "Idle"]);
+        assert_eq!(groups[1].motions[1].index, 1);
+        assert_eq!(groups[1].motions[1].file, "motions/idle_01.motion3.json");
+    }
+}
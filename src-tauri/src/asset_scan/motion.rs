@@ -0,0 +1,287 @@
+use super::{extension, join_reference, live2d, ReferenceKind};
+use crate::file_server::archive::AssetFs;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+
+/// Cubism 2 `.mtn` 未写 `$fps` 时的帧率
+const DEFAULT_MTN_FPS: f64 = 30.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionFormat {
+    /// Cubism 2 的 `.mtn`
+    Mtn,
+    /// Cubism 3/4 的 `.motion3.json`
+    Motion3,
+}
+
+/// 动作曲线控制的对象
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurveTarget {
+    /// `Parameter`、`PartOpacity` 或 `Model`；Cubism 2 的 `LAYOUT:` 曲线为 `Layout`
+    pub target: String,
+    pub id: String,
+}
+
+/// 动作文件的时间信息，时间单位为秒
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MotionInfo {
+    pub format: MotionFormat,
+    pub duration: f64,
+    pub fps: f64,
+    /// Cubism 2 的动作没有循环标志
+    #[serde(rename = "loop")]
+    pub looped: Option<bool>,
+    /// 文件中未写时为空，由播放器使用默认值
+    pub fade_in: Option<f64>,
+    pub fade_out: Option<f64>,
+    pub curves: Vec<CurveTarget>,
+}
+
+/// 模型清单中的一个动作及其时间信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelMotion {
+    pub group: String,
+    pub index: usize,
+    /// 相对于模型文件所在目录的路径
+    pub file: String,
+    /// 动作来自 JSONL 的哪个子模型
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub_model: Option<String>,
+    pub info: Option<MotionInfo>,
+    /// 动作文件无法读取或解析时的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 列出 Live2D 模型（或 JSONL 中每个子模型）的所有动作，并读取它们的时间信息
+pub fn model_motions(asset_fs: &AssetFs, model_path: &Path) -> Result<Vec<ModelMotion>, String> {
+    let content = asset_fs
+        .read_to_string(model_path)
+        .map_err(|e| format!("读取文件失败: {}", e))?;
+    let model_dir = model_path.parent().unwrap_or(Path::new(""));
+
+    if extension(model_path) != "jsonl" {
+        let json = serde_json::from_str::<Value>(&content)
+            .map_err(|e| format!("解析 JSON 文件失败: {}", e))?;
+        let manifest = live2d::resolve(&json).ok_or_else(|| "不是 Live2D 模型文件".to_string())?;
+        return Ok(manifest_motions(asset_fs, model_dir, "", &manifest, None));
+    }
+
+    // JSONL 每行的 `path` 指向一个子模型，动作路径以子模型所在目录为基准
+    let mut motions = Vec::new();
+    for sub_model in live2d::jsonl_sub_models(asset_fs, model_path).loaded {
+        let prefix = Path::new(&sub_model.path)
+            .parent()
+            .map(|dir| dir.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let sub_dir = sub_model.full_path.parent().unwrap_or(model_dir);
+        motions.extend(manifest_motions(
+            asset_fs,
+            sub_dir,
+            &prefix,
+            &sub_model.manifest,
+            Some(&sub_model.path),
+        ));
+    }
+    Ok(motions)
+}
+
+fn manifest_motions(
+    asset_fs: &AssetFs,
+    manifest_dir: &Path,
+    prefix: &str,
+    manifest: &live2d::Live2dManifest,
+    sub_model: Option<&str>,
+) -> Vec<ModelMotion> {
+    manifest
+        .references
+        .iter()
+        .filter(|reference| reference.kind == ReferenceKind::Motion)
+        .map(|reference| {
+            let parsed = join_reference(manifest_dir, &reference.file)
+                .ok_or_else(|| format!("无效的动作路径: {}", reference.file))
+                .and_then(|path| parse_motion(asset_fs, &path));
+            let file = if prefix.is_empty() {
+                reference.file.clone()
+            } else {
+                format!("{}/{}", prefix, reference.file)
+            };
+            ModelMotion {
+                group: reference.name.clone().unwrap_or_default(),
+                index: reference.index.unwrap_or_default(),
+                file,
+                sub_model: sub_model.map(str::to_string),
+                error: parsed.as_ref().err().cloned(),
+                info: parsed.ok(),
+            }
+        })
+        .collect()
+}
+
+/// 按扩展名解析 `.mtn` 或 `.motion3.json`
+///
+/// 旧的 `.mtn` 常以 Shift-JIS 等非 UTF-8 编码保存注释，因此按字节读取后有损解码
+pub fn parse_motion(asset_fs: &AssetFs, path: &Path) -> Result<MotionInfo, String> {
+    let bytes = asset_fs
+        .read(path)
+        .map_err(|e| format!("读取动作文件失败: {}", e))?;
+    let content = String::from_utf8_lossy(&bytes);
+    match extension(path).as_str() {
+        "mtn" => Ok(parse_mtn(&content)),
+        "json" => parse_motion3(&content),
+        ext => Err(format!("不支持的动作文件类型: {}", ext)),
+    }
+}
+
+/// Cubism 2 `.mtn` 是文本格式：`#` 开头为注释，`$fps=30` 等为元数据，
+/// 其余每行 `ID=v1,v2,...` 是一条按帧采样的曲线
+fn parse_mtn(content: &str) -> MotionInfo {
+    let mut fps = DEFAULT_MTN_FPS;
+    let mut fade_in = None;
+    let mut fade_out = None;
+    let mut frames = 0;
+    let mut curves = Vec::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim(), value.trim());
+        if let Some(meta) = key.strip_prefix('$') {
+            // 淡入淡出以毫秒为单位
+            match meta {
+                "fps" => fps = value.parse().unwrap_or(DEFAULT_MTN_FPS),
+                "fadein" => fade_in = value.parse::<f64>().ok().map(|ms| ms / 1000.0),
+                "fadeout" => fade_out = value.parse::<f64>().ok().map(|ms| ms / 1000.0),
+                _ => {}
+            }
+            continue;
+        }
+
+        frames = frames.max(value.split(',').filter(|v| !v.trim().is_empty()).count());
+        let (target, id) = match key.split_once(':') {
+            Some(("VISIBLE", id)) => ("PartOpacity", id),
+            Some(("LAYOUT", id)) => ("Layout", id),
+            _ => ("Parameter", key),
+        };
+        curves.push(CurveTarget {
+            target: target.to_string(),
+            id: id.to_string(),
+        });
+    }
+
+    let fps = if fps > 0.0 { fps } else { DEFAULT_MTN_FPS };
+    MotionInfo {
+        format: MotionFormat::Mtn,
+        duration: frames as f64 / fps,
+        fps,
+        looped: None,
+        fade_in,
+        fade_out,
+        curves,
+    }
+}
+
+/// Cubism 3/4 `.motion3.json`：时间信息在 `Meta` 中，曲线在 `Curves[].Target/Id`
+fn parse_motion3(content: &str) -> Result<MotionInfo, String> {
+    let json =
+        serde_json::from_str::<Value>(content).map_err(|e| format!("解析动作文件失败: {}", e))?;
+    let meta = json
+        .get("Meta")
+        .ok_or_else(|| "动作文件缺少 Meta 字段".to_string())?;
+    let number = |key: &str| meta.get(key).and_then(|v| v.as_f64());
+
+    let curves = json
+        .get("Curves")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|curve| {
+            Some(CurveTarget {
+                target: curve.get("Target")?.as_str()?.to_string(),
+                id: curve.get("Id")?.as_str()?.to_string(),
+            })
+        })
+        .collect();
+
+    Ok(MotionInfo {
+        format: MotionFormat::Motion3,
+        duration: number("Duration").unwrap_or_default(),
+        fps: number("Fps").unwrap_or_default(),
+        looped: meta.get("Loop").and_then(|v| v.as_bool()),
+        fade_in: number("FadeInTime"),
+        fade_out: number("FadeOutTime"),
+        curves,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mtn_duration_uses_fps_line() {
+        let info = parse_mtn("# comment\n$fps=10\nPARAM_ANGLE_X=0,1,2,3,4\nVISIBLE:PARTS_01=1,1\n");
+        assert_eq!(info.fps, 10.0);
+        assert_eq!(info.duration, 0.5);
+        assert_eq!(info.curves.len(), 2);
+        assert_eq!(info.curves[1].target, "PartOpacity");
+        assert_eq!(info.curves[1].id, "PARTS_01");
+    }
+
+    #[test]
+    fn mtn_without_fps_uses_default() {
+        let info = parse_mtn("PARAM_ANGLE_X=0,1,2\n");
+        assert_eq!(info.fps, DEFAULT_MTN_FPS);
+        assert_eq!(info.duration, 3.0 / DEFAULT_MTN_FPS);
+        assert_eq!(info.fade_in, None);
+    }
+
+    #[test]
+    fn mtn_fades_are_converted_to_seconds() {
+        let info = parse_mtn("$fadein=500\n$fadeout=250\nPARAM_ANGLE_X=0\n");
+        assert_eq!(info.fade_in, Some(0.5));
+        assert_eq!(info.fade_out, Some(0.25));
+    }
+
+    #[test]
+    fn mtn_with_non_utf8_comment_is_parsed() {
+        // Shift-JIS 编码的注释「モーション」
+        let bytes = b"# \x83\x82\x81[\x83V\x83\x87\x83\x93\n$fps=30\nPARAM_ANGLE_X=0,1,2\n";
+        let path = std::env::temp_dir().join(format!("webgal-mtn-{}.mtn", rand::random::<u32>()));
+        std::fs::write(&path, bytes).unwrap();
+        let info = parse_motion(&AssetFs::Disk, &path);
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(info.unwrap().duration, 0.1);
+    }
+
+    #[test]
+    fn motion3_reads_meta() {
+        let info = parse_motion3(
+            r#"{
+                "Meta": { "Duration": 2.5, "Fps": 30, "Loop": true, "FadeInTime": 0.5 },
+                "Curves": [{ "Target": "Parameter", "Id": "ParamAngleX" }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(info.duration, 2.5);
+        assert_eq!(info.fps, 30.0);
+        assert_eq!(info.looped, Some(true));
+        assert_eq!(info.fade_in, Some(0.5));
+        assert_eq!(info.fade_out, None);
+        assert_eq!(info.curves[0].id, "ParamAngleX");
+    }
+
+    #[test]
+    fn motion3_without_meta_is_an_error() {
+        assert!(parse_motion3(r#"{ "Curves": [] }"#).is_err());
+    }
+}
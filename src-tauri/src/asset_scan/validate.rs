@@ -0,0 +1,462 @@
+use super::{
+    analyze, join_reference, live2d, relative_path, walk_reference, AssetKind, ReferenceKind,
+};
+use crate::file_server::archive::AssetFs;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// 立绘文件的校验结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FigureReport {
+    pub kind: Option<AssetKind>,
+    pub valid: bool,
+    pub issues: Vec<FigureIssue>,
+}
+
+/// 校验发现的问题，路径都相对于被校验文件所在目录并使用 `/` 分隔
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum FigureIssue {
+    /// 引用的文件不存在
+    MissingFile {
+        path: String,
+        kind: ReferenceKind,
+        referenced_by: String,
+    },
+    /// 文件存在但无法读取，例如不是 UTF-8 编码
+    UnreadableFile { path: String, error: String },
+    /// 无法解析的 JSON；`line` 是 JSONL 中的行号（从 1 开始）
+    MalformedJson {
+        path: String,
+        line: Option<usize>,
+        error: String,
+    },
+    /// 文件存在但大小写与引用不一致，在区分大小写的文件系统（如 Linux 服务器）上会加载失败
+    CaseMismatch {
+        path: String,
+        actual_path: String,
+        kind: ReferenceKind,
+        referenced_by: String,
+    },
+    /// JSONL 的子模型不是 Live2D 模型，编辑器无法读取其中的动作和表情
+    InvalidSubModel { path: String, referenced_by: String },
+    UnreachableMotion {
+        group: String,
+        index: Option<usize>,
+        referenced_by: String,
+        reason: UnreachableReason,
+    },
+    UnreachableExpression {
+        name: String,
+        index: Option<usize>,
+        referenced_by: String,
+        reason: UnreachableReason,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnreachableReason {
+    /// 条目没有写文件路径
+    NoFile,
+    /// JSONL 汇总行中列出，但没有任何子模型提供
+    NotInAnySubModel,
+}
+
+/// 引用的文件在区分大小写时的查找结果
+enum Located {
+    Exact(PathBuf),
+    CaseMismatch(PathBuf),
+    Missing,
+}
+
+/// 校验 Live2D 模型、JSONL 聚合模型或 Mano 立绘引用的文件
+///
+/// 在 Windows 上也按区分大小写的规则检查路径，提前发现部署到 Linux 服务器后才会出现的问题
+pub fn validate_figure(asset_fs: &AssetFs, path: &Path) -> Result<FigureReport, String> {
+    if !asset_fs.is_file(path) {
+        return Err(format!("文件不存在: {:?}", path));
+    }
+    let root = path
+        .parent()
+        .ok_or_else(|| format!("无效的路径: {:?}", path))?;
+
+    let mut validator = Validator {
+        asset_fs,
+        root,
+        dir_entries: HashMap::new(),
+        issues: Vec::new(),
+    };
+    match super::extension(path).as_str() {
+        "jsonl" => validator.jsonl(path),
+        "json" => {
+            if let Some(json) = validator.read_json(path) {
+                validator.model(path, &json);
+            }
+        }
+        ext => return Err(format!("不支持校验的文件类型: {}", ext)),
+    }
+
+    let issues = validator.issues;
+    Ok(FigureReport {
+        kind: analyze(asset_fs, path, root).kind,
+        valid: issues.is_empty(),
+        issues,
+    })
+}
+
+/// 一个 JSONL 中所有子模型提供的动作分组和表情名称
+#[derive(Default)]
+struct Provided {
+    motions: BTreeSet<String>,
+    expressions: BTreeSet<String>,
+}
+
+struct Validator<'a> {
+    asset_fs: &'a AssetFs,
+    root: &'a Path,
+    /// 目录 -> 其中的文件名，避免对同一目录重复列出
+    dir_entries: HashMap<PathBuf, Vec<String>>,
+    issues: Vec<FigureIssue>,
+}
+
+impl Validator<'_> {
+    fn display(&self, path: &Path) -> String {
+        relative_path(path, self.root).unwrap_or_else(|_| path.to_string_lossy().to_string())
+    }
+
+    fn read_to_string(&mut self, path: &Path) -> Option<String> {
+        match self.asset_fs.read_to_string(path) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                self.issues.push(FigureIssue::UnreadableFile {
+                    path: self.display(path),
+                    error: e.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    fn read_json(&mut self, path: &Path) -> Option<Value> {
+        let content = self.read_to_string(path)?;
+        match serde_json::from_str::<Value>(&content) {
+            Ok(json) => Some(json),
+            Err(e) => {
+                self.issues.push(FigureIssue::MalformedJson {
+                    path: self.display(path),
+                    line: None,
+                    error: e.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// JSONL 每行的 `path` 指向一个子模型，汇总行的 `motions`/`expressions` 必须由某个子模型提供
+    fn jsonl(&mut self, path: &Path) {
+        let Some(content) = self.read_to_string(path) else {
+            return;
+        };
+        let referenced_by = self.display(path);
+        let mut provided = Provided::default();
+        let mut declared_motions = Vec::new();
+        let mut declared_expressions = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let json = match serde_json::from_str::<Value>(line) {
+                Ok(json) => json,
+                Err(e) => {
+                    self.issues.push(FigureIssue::MalformedJson {
+                        path: referenced_by.clone(),
+                        line: Some(i + 1),
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            if let Some(sub_model) = json.get("path").and_then(|p| p.as_str()) {
+                let sub_model_path = match self.reference(
+                    self.root,
+                    sub_model,
+                    ReferenceKind::SubModel,
+                    &referenced_by,
+                ) {
+                    Some(sub_model_path) => sub_model_path,
+                    None => continue,
+                };
+                let Some(sub_model_json) = self.read_json(&sub_model_path) else {
+                    continue;
+                };
+                let Some(manifest) = self.model(&sub_model_path, &sub_model_json) else {
+                    self.issues.push(FigureIssue::InvalidSubModel {
+                        path: self.display(&sub_model_path),
+                        referenced_by: referenced_by.clone(),
+                    });
+                    continue;
+                };
+                for reference in manifest.references {
+                    let Some(name) = reference.name else {
+                        continue;
+                    };
+                    match reference.kind {
+                        ReferenceKind::Motion => provided.motions.insert(name),
+                        ReferenceKind::Expression => provided.expressions.insert(name),
+                        _ => false,
+                    };
+                }
+            } else {
+                declared_motions.extend(live2d::declared_names(json.get("motions")));
+                declared_expressions.extend(live2d::declared_names(json.get("expressions")));
+            }
+        }
+
+        for group in declared_motions {
+            if !provided.motions.contains(&group) {
+                self.issues.push(FigureIssue::UnreachableMotion {
+                    group,
+                    index: None,
+                    referenced_by: referenced_by.clone(),
+                    reason: UnreachableReason::NotInAnySubModel,
+                });
+            }
+        }
+        for name in declared_expressions {
+            if !provided.expressions.contains(&name) {
+                self.issues.push(FigureIssue::UnreachableExpression {
+                    name,
+                    index: None,
+                    referenced_by: referenced_by.clone(),
+                    reason: UnreachableReason::NotInAnySubModel,
+                });
+            }
+        }
+    }
+
+    /// 校验 Live2D 清单或 Mano 立绘引用的文件，返回 Live2D 清单供 JSONL 汇总
+    fn model(&mut self, path: &Path, json: &Value) -> Option<live2d::Live2dManifest> {
+        let dir = path.parent().unwrap_or(self.root);
+        let referenced_by = self.display(path);
+
+        let manifest = live2d::resolve(json);
+        if let Some(manifest) = &manifest {
+            for reference in &manifest.references {
+                self.reference(dir, &reference.file, reference.kind, &referenced_by);
+            }
+            for entry in &manifest.entries_without_file {
+                let name = entry.name.clone().unwrap_or_default();
+                self.issues.push(match entry.kind {
+                    ReferenceKind::Motion => FigureIssue::UnreachableMotion {
+                        group: name,
+                        index: entry.index,
+                        referenced_by: referenced_by.clone(),
+                        reason: UnreachableReason::NoFile,
+                    },
+                    _ => FigureIssue::UnreachableExpression {
+                        name,
+                        index: entry.index,
+                        referenced_by: referenced_by.clone(),
+                        reason: UnreachableReason::NoFile,
+                    },
+                });
+            }
+        }
+
+        // Mano：assets.layers[].path
+        let layers = json
+            .get("assets")
+            .and_then(|assets| assets.get("layers"))
+            .and_then(|l| l.as_array());
+        let layer_paths: Vec<&str> = layers
+            .into_iter()
+            .flatten()
+            .filter_map(|layer| layer.get("path").and_then(|p| p.as_str()))
+            .collect();
+        for layer_path in layer_paths {
+            self.reference(dir, layer_path, ReferenceKind::Layer, &referenced_by);
+        }
+
+        manifest
+    }
+
+    /// 检查 `dir` 下的引用是否存在，找到时返回实际路径
+    fn reference(
+        &mut self,
+        dir: &Path,
+        file: &str,
+        kind: ReferenceKind,
+        referenced_by: &str,
+    ) -> Option<PathBuf> {
+        let path = join_reference(dir, file).unwrap_or_else(|| dir.join(file));
+        match self.locate(dir, file) {
+            Located::Exact(actual) => Some(actual),
+            Located::CaseMismatch(actual) => {
+                self.issues.push(FigureIssue::CaseMismatch {
+                    path: self.display(&path),
+                    actual_path: self.display(&actual),
+                    kind,
+                    referenced_by: referenced_by.to_string(),
+                });
+                Some(actual)
+            }
+            Located::Missing => {
+                self.issues.push(FigureIssue::MissingFile {
+                    path: self.display(&path),
+                    kind,
+                    referenced_by: referenced_by.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// 逐级列出目录，按区分大小写的规则查找 `file`
+    fn locate(&mut self, dir: &Path, file: &str) -> Located {
+        let mut mismatched = false;
+        let found = walk_reference(dir, file, |current, part| {
+            let entries = self.entries(current);
+            if entries.iter().any(|entry| entry == part) {
+                return Some(part.to_string());
+            }
+            let lower = part.to_lowercase();
+            let entry = entries.iter().find(|entry| entry.to_lowercase() == lower)?;
+            mismatched = true;
+            Some(entry.clone())
+        });
+        let Some(current) = found else {
+            return Located::Missing;
+        };
+
+        if !self.asset_fs.is_file(&current) {
+            Located::Missing
+        } else if mismatched {
+            Located::CaseMismatch(current)
+        } else {
+            Located::Exact(current)
+        }
+    }
+
+    fn entries(&mut self, dir: &Path) -> &Vec<String> {
+        let asset_fs = self.asset_fs;
+        self.dir_entries
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                asset_fs
+                    .read_dir(dir)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|entry| entry.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+                    .collect()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "webgal-validate-{}-{}",
+            name,
+            rand::random::<u32>()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(root: &Path, relative: &str, content: &[u8]) {
+        let path = root.join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn model3(textures: &[&str]) -> Vec<u8> {
+        serde_json::json!({
+            "Version": 3,
+            "FileReferences": { "Moc": "model.moc3", "Textures": textures }
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn missing_texture_is_reported() {
+        let root = temp_root("texture");
+        write(&root, "model.moc3", b"");
+        write(
+            &root,
+            "model.model3.json",
+            &model3(&["textures/texture_00.png"]),
+        );
+
+        let report = validate_figure(&AssetFs::Disk, &root.join("model.model3.json")).unwrap();
+        let _ = fs::remove_dir_all(&root);
+        assert!(!report.valid);
+        assert!(matches!(
+            report.issues.as_slice(),
+            [FigureIssue::MissingFile { path, kind: ReferenceKind::Texture, .. }]
+                if path == "textures/texture_00.png"
+        ));
+    }
+
+    #[test]
+    fn parent_references_are_resolved() {
+        let root = temp_root("parent");
+        write(&root, "model/model.moc3", b"");
+        write(&root, "shared/texture_00.png", b"");
+        write(
+            &root,
+            "model/model.model3.json",
+            &model3(&["../shared/texture_00.png", "../shared/texture_01.png"]),
+        );
+        write(
+            &root,
+            "figure.jsonl",
+            b"{\"path\":\"model/model.model3.json\"}\n",
+        );
+
+        let report = validate_figure(&AssetFs::Disk, &root.join("figure.jsonl")).unwrap();
+        let _ = fs::remove_dir_all(&root);
+        assert!(matches!(
+            report.issues.as_slice(),
+            [FigureIssue::MissingFile { path, referenced_by, .. }]
+                if path == "shared/texture_01.png" && referenced_by == "model/model.model3.json"
+        ));
+    }
+
+    #[test]
+    fn failed_sub_models_are_reported() {
+        let root = temp_root("sub-model");
+        write(&root, "not_a_model.json", b"{\"foo\":1}");
+        write(&root, "not_utf8.model3.json", b"{\"Version\":3,\"\xff\":1}");
+        write(
+            &root,
+            "figure.jsonl",
+            b"{\"path\":\"not_a_model.json\"}\n{\"path\":\"not_utf8.model3.json\"}\n",
+        );
+
+        let report = validate_figure(&AssetFs::Disk, &root.join("figure.jsonl")).unwrap();
+        let _ = fs::remove_dir_all(&root);
+        assert!(matches!(
+            report.issues.as_slice(),
+            [
+                FigureIssue::InvalidSubModel { path: invalid, .. },
+                FigureIssue::UnreadableFile { path: unreadable, .. },
+            ] if invalid == "not_a_model.json" && unreadable == "not_utf8.model3.json"
+        ));
+    }
+}
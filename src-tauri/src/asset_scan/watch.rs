@@ -0,0 +1,308 @@
+use super::{scan_paths, ScanControl};
+use crate::file_server::archive::AssetFs;
+use crate::file_server::watch::{in_scope, AssetChanges, AssetWatcher, ChangeHub};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// 立绘或背景列表变化时发送给 webview 的 Tauri 事件名
+pub const ASSETS_CHANGED_EVENT: &str = "assets-changed";
+
+/// 等待文件变化时检查停止标志的间隔
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 被监视的素材文件夹
+const ASSET_FOLDERS: [&str; 2] = ["figure", "background"];
+
+/// 一个素材文件夹中可选择素材的增减，路径与 `scan_directory_recursive` 的结果相同
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetListChanges {
+    /// `figure` 或 `background`
+    pub folder: String,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+type AssetListListener = Box<dyn Fn(&AssetListChanges) + Send>;
+
+/// 一个素材文件夹及其当前的素材列表
+struct WatchedFolder {
+    name: &'static str,
+    /// 扫描用的路径，可能位于 zip 内
+    path: PathBuf,
+    /// 文件变化中该文件夹的路径，相对于游戏文件夹，如 `game/figure`
+    scope: String,
+    assets: Vec<String>,
+}
+
+impl WatchedFolder {
+    fn exists(&self) -> bool {
+        AssetFs::for_path(&self.path).is_ok_and(|asset_fs| asset_fs.is_dir(&self.path))
+    }
+
+    fn is_affected_by(&self, changes: &AssetChanges) -> bool {
+        changes
+            .changed
+            .iter()
+            .chain(&changes.added)
+            .chain(&changes.removed)
+            .any(|path| in_scope(path, &self.scope))
+    }
+
+    /// 重新扫描；文件夹不存在时没有素材，zip 正在写入等其他错误由调用方保留之前的列表
+    fn scan(&self, control: &ScanControl) -> Result<Vec<String>, String> {
+        let asset_fs =
+            AssetFs::for_path(&self.path).map_err(|e| format!("读取 zip 失败: {}", e))?;
+        if !asset_fs.is_dir(&self.path) {
+            return Ok(Vec::new());
+        }
+        scan_paths(&asset_fs, &self.path, control)
+    }
+
+    /// 用新的扫描结果替换素材列表，返回增减的素材
+    fn update(&mut self, assets: Vec<String>) -> AssetListChanges {
+        let previous: HashSet<&String> = self.assets.iter().collect();
+        let current: HashSet<&String> = assets.iter().collect();
+        let changes = AssetListChanges {
+            folder: self.name.to_string(),
+            added: assets
+                .iter()
+                .filter(|asset| !previous.contains(asset))
+                .cloned()
+                .collect(),
+            removed: self
+                .assets
+                .iter()
+                .filter(|asset| !current.contains(asset))
+                .cloned()
+                .collect(),
+        };
+        self.assets = assets;
+        changes
+    }
+
+    /// 重新扫描并在列表有增减时通知监听器
+    fn refresh(&mut self, control: &ScanControl, listener: &AssetListListener) {
+        let assets = match self.scan(control) {
+            Ok(assets) => assets,
+            Err(e) => {
+                log::debug!("暂时无法扫描 {:?}: {}", self.path, e);
+                return;
+            }
+        };
+        let changes = self.update(assets);
+        if !changes.added.is_empty() || !changes.removed.is_empty() {
+            log::debug!(
+                "{} 素材变化: {} 个新增, {} 个删除",
+                changes.folder,
+                changes.added.len(),
+                changes.removed.len()
+            );
+            listener(&changes);
+        }
+    }
+}
+
+/// 自行监视已存在的素材文件夹，各文件夹的变化加上其范围（如 `game/figure`）后汇总到返回的接收端
+fn watch_folders(
+    folders: &[WatchedFolder],
+) -> Result<(Vec<AssetWatcher>, Receiver<Arc<AssetChanges>>), String> {
+    let hub = Arc::new(ChangeHub::default());
+    let receiver = hub.subscribe();
+    let mut watchers = Vec::new();
+    for folder in folders.iter().filter(|folder| folder.exists()) {
+        let folder_hub = Arc::new(ChangeHub::default());
+        let scope = folder.scope.clone();
+        let hub = Arc::clone(&hub);
+        folder_hub.set_listener(move |changes| hub.publish(with_scope(changes, &scope)));
+        match AssetWatcher::start(&folder.path.to_string_lossy(), folder_hub) {
+            Ok(watcher) => watchers.push(watcher),
+            Err(e) => {
+                watchers.into_iter().for_each(AssetWatcher::stop);
+                return Err(e);
+            }
+        }
+    }
+    Ok((watchers, receiver))
+}
+
+/// 把相对于素材文件夹的路径转换为相对于游戏文件夹的路径
+fn with_scope(changes: &AssetChanges, scope: &str) -> AssetChanges {
+    let prefix = |paths: &[String]| -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| match path.as_str() {
+                "" => scope.to_string(),
+                path => format!("{}/{}", scope, path),
+            })
+            .collect()
+    };
+    AssetChanges {
+        changed: prefix(&changes.changed),
+        added: prefix(&changes.added),
+        removed: prefix(&changes.removed),
+    }
+}
+
+/// 根据游戏文件夹的文件变化重新计算 `game/figure` 和 `game/background` 中可选择的素材，
+/// 按与扫描相同的归入规则，把列表的增减通知给监听器
+///
+/// 文件变化来自 [`AssetWatcher`]，重新扫描使用扫描索引，只有变化过的文件会被重新解析
+///
+/// 借用的文件服务器订阅断开（服务器停止或切换根目录）后，改为自行监视素材文件夹
+pub struct GameAssetWatcher {
+    /// 停止时设置，正在进行的扫描随之中止
+    stopped: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl GameAssetWatcher {
+    /// `changes` 是文件服务器对同一游戏文件夹的变化订阅，为空时自行监视素材文件夹
+    pub fn start(
+        game_folder: &str,
+        changes: Option<Receiver<Arc<AssetChanges>>>,
+        index_dir: Option<PathBuf>,
+        listener: impl Fn(&AssetListChanges) + Send + 'static,
+    ) -> Result<Self, String> {
+        let game_dir = Path::new(game_folder).join("game");
+        let folders: Vec<WatchedFolder> = ASSET_FOLDERS
+            .into_iter()
+            .map(|name| WatchedFolder {
+                name,
+                path: game_dir.join(name),
+                scope: format!("game/{}", name),
+                assets: Vec::new(),
+            })
+            .collect();
+        if !folders.iter().any(WatchedFolder::exists) {
+            return Err(format!("找不到素材文件夹: {:?}", game_dir));
+        }
+
+        let (watchers, receiver) = match changes {
+            Some(receiver) => (Vec::new(), receiver),
+            None => watch_folders(&folders)?,
+        };
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let control = ScanControl::new(None, Arc::clone(&stopped), |_| {});
+        let control = match index_dir {
+            Some(index_dir) => control.with_index_dir(index_dir),
+            None => control,
+        };
+        let listener: AssetListListener = Box::new(listener);
+        let thread = {
+            let stopped = Arc::clone(&stopped);
+            std::thread::spawn(move || {
+                let mut watchers = watchers;
+                watch_loop(
+                    folders,
+                    &control,
+                    receiver,
+                    &mut watchers,
+                    &listener,
+                    &stopped,
+                );
+                watchers.into_iter().for_each(AssetWatcher::stop);
+            })
+        };
+        log::info!("开始监视素材文件夹: {}", game_folder);
+        Ok(Self { stopped, thread })
+    }
+
+    /// 停止监视：中止正在进行的扫描并等待监视线程退出，自行建立的监视随之停止
+    pub fn stop(self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if self.thread.join().is_err() {
+            log::error!("素材监视线程异常退出");
+        }
+    }
+}
+
+/// `watchers` 是自行建立的监视，订阅断开后新建的监视也加入其中，由调用方在退出后停止
+fn watch_loop(
+    mut folders: Vec<WatchedFolder>,
+    control: &ScanControl,
+    mut receiver: Receiver<Arc<AssetChanges>>,
+    watchers: &mut Vec<AssetWatcher>,
+    listener: &AssetListListener,
+    stopped: &AtomicBool,
+) {
+    for folder in &mut folders {
+        match folder.scan(control) {
+            Ok(assets) => folder.assets = assets,
+            Err(e) => log::debug!("暂时无法扫描 {:?}: {}", folder.path, e),
+        }
+    }
+
+    loop {
+        let first = match receiver.recv_timeout(STOP_POLL_INTERVAL) {
+            Ok(changes) => changes,
+            Err(RecvTimeoutError::Timeout) => {
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                continue;
+            }
+            // 文件服务器停止或切换了根目录，改为自行监视
+            Err(RecvTimeoutError::Disconnected) => {
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+                match watch_folders(&folders) {
+                    Ok((own, own_receiver)) => {
+                        log::info!("文件服务器的监视已结束，改为自行监视素材文件夹");
+                        watchers.extend(own);
+                        receiver = own_receiver;
+                    }
+                    Err(e) => {
+                        log::warn!("无法继续监视素材文件夹: {}", e);
+                        return;
+                    }
+                }
+                // 切换期间的变化没有通知，重新扫描全部文件夹
+                for folder in &mut folders {
+                    folder.refresh(control, listener);
+                }
+                continue;
+            }
+        };
+        if stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        // 变化已经过去抖，这里只合并已经到达的通知
+        let batch: Vec<Arc<AssetChanges>> =
+            std::iter::once(first).chain(receiver.try_iter()).collect();
+
+        // 贴图的增删也会改变列表（模型引用的贴图被归入模型），因此整个文件夹按遍历顺序重新归入
+        for folder in &mut folders {
+            if batch.iter().any(|changes| folder.is_affected_by(changes)) {
+                folder.refresh(control, listener);
+            }
+        }
+    }
+}
+
+/// 当前的素材监视器，切换游戏文件夹时替换
+#[derive(Default)]
+pub struct GameAssetWatchState {
+    watcher: Mutex<Option<GameAssetWatcher>>,
+}
+
+impl GameAssetWatchState {
+    /// 停止当前的监视器并换成 `watcher`，传入 `None` 时只停止
+    pub fn replace(&self, watcher: Option<GameAssetWatcher>) {
+        let previous = match self.watcher.lock() {
+            Ok(mut current) => std::mem::replace(&mut *current, watcher),
+            Err(_) => None,
+        };
+        if let Some(previous) = previous {
+            previous.stop();
+        }
+    }
+}
@@ -1,135 +1,742 @@
-use std::fs;
-use std::path::Path;
-use std::sync::Arc;
-use tiny_http::{Response, Server};
-use urlencoding;
+pub mod access_log;
+pub mod archive;
+pub mod asset;
+pub mod auth;
+pub mod compression;
+pub mod cors;
+pub mod events;
+pub mod mime;
+pub mod thumbnail;
+pub mod watch;
 
-pub fn start_file_server(port: u16, base_path: String) -> Option<std::thread::JoinHandle<()>> {
-    let address = format!("127.0.0.1:{}", port);
+use access_log::{AccessLog, AccessLogEntry};
+use archive::ArchiveCache;
+use asset::{serve_asset, AssetBody, AssetRequest, AssetResponse};
+use compression::CompressionCache;
+use cors::OriginList;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Request, Response, ResponseBox, Server, StatusCode};
+use watch::{AssetChanges, AssetWatcher, ChangeHub};
 
-    match Server::http(&address) {
-        Ok(server) => {
-            println!("✅ 文件服务器已启动: http://{}", address);
-            let base_path_arc = Arc::new(base_path);
-
-            let handle = std::thread::spawn(move || {
-                for request in server.incoming_requests() {
-                    let url = request.url();
-                    let base_path = Arc::clone(&base_path_arc);
-
-                    // 处理请求
-                    let response = handle_request(url, base_path);
-                    match request.respond(response) {
-                        Ok(_) => {}
-                        Err(e) => eprintln!("发送响应失败: {}", e),
-                    }
-                }
-            });
+/// 默认的 Cache-Control：允许 webview 缓存，但每次使用前都通过 ETag 重新验证
+pub const DEFAULT_CACHE_CONTROL: &str = "no-cache";
+
+/// 默认工作线程数
+pub const DEFAULT_WORKER_COUNT: usize = 8;
+
+/// 工作线程数上限，前端传入更大的值时按上限处理
+pub const MAX_WORKER_COUNT: usize = 64;
+
+/// 默认等待队列长度，队列满时新请求直接返回 503
+pub const DEFAULT_QUEUE_CAPACITY: usize = 256;
+
+/// 自定义资源协议名，注册后可以通过 `webgal-asset://` 加载游戏资源而无需开放端口
+pub const ASSET_PROTOCOL: &str = "webgal-asset";
 
-            Some(handle)
+/// 资源协议在 webview 中的基础 URL
+///
+/// Windows 和 Android 上 webview 会把自定义协议映射为 `http://<scheme>.localhost`
+pub fn protocol_base_url() -> String {
+    if cfg!(any(windows, target_os = "android")) {
+        format!("http://{}.localhost", ASSET_PROTOCOL)
+    } else {
+        format!("{}://localhost", ASSET_PROTOCOL)
+    }
+}
+
+/// 向 webview 提供资源的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// 在 127.0.0.1 上启动 HTTP 服务器
+    #[default]
+    Http,
+    /// 使用 `webgal-asset://` 自定义协议，不占用端口
+    Protocol,
+}
+
+/// 命名挂载点表：键为 URL 的第一段路径，值为对应的目录
+pub type MountTable = Arc<RwLock<BTreeMap<String, String>>>;
+
+/// 文件服务器配置
+#[derive(Debug, Clone)]
+pub struct FileServerConfig {
+    /// 提供文件的根目录，未命中任何挂载点的请求都从这里读取；也可以是 zip 文件
+    pub base_path: String,
+    /// 命名挂载点，`/<name>/...` 会映射到对应目录或 zip，优先于根目录下的同名子目录
+    pub mounts: MountTable,
+    /// 附加在文件响应上的 Cache-Control 值
+    pub cache_control: String,
+    /// 并行处理请求的工作线程数
+    pub worker_count: usize,
+    /// 等待工作线程处理的请求队列长度
+    pub queue_capacity: usize,
+    /// 压缩结果缓存，HTTP 服务器和资源协议共享
+    pub compression_cache: Arc<CompressionCache>,
+    /// 允许跨域读取资源的 Origin 列表
+    pub allowed_origins: OriginList,
+    /// 会话访问令牌，设置后缺少令牌的请求返回 401
+    pub access_token: Option<String>,
+    /// 访问日志，HTTP 服务器和资源协议共享
+    pub access_log: Arc<AccessLog>,
+    /// 缩略图的磁盘缓存目录
+    pub thumbnail_dir: PathBuf,
+    /// zip 索引缓存，根目录或挂载点是 zip 时使用
+    pub archives: Arc<ArchiveCache>,
+    /// 根目录的文件变化，事件流从这里订阅
+    pub change_hub: Arc<ChangeHub>,
+    /// 当前打开的事件流连接数
+    pub event_streams: Arc<AtomicUsize>,
+}
+
+impl FileServerConfig {
+    pub fn new(base_path: String) -> Self {
+        Self {
+            base_path,
+            mounts: MountTable::default(),
+            cache_control: DEFAULT_CACHE_CONTROL.to_string(),
+            worker_count: DEFAULT_WORKER_COUNT,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            compression_cache: Arc::default(),
+            allowed_origins: cors::default_origins(),
+            access_token: None,
+            access_log: Arc::default(),
+            thumbnail_dir: thumbnail::default_thumbnail_dir(),
+            archives: Arc::default(),
+            change_hub: Arc::default(),
+            event_streams: Arc::default(),
         }
-        Err(e) => {
-            eprintln!("启动文件服务器失败: {}", e);
-            None
+    }
+}
+
+/// 请求队列与工作线程的运行计数
+#[derive(Default)]
+struct ServerStats {
+    queued: AtomicUsize,
+    peak_queued: AtomicUsize,
+    active: AtomicUsize,
+    completed: AtomicU64,
+    rejected: AtomicU64,
+    total_wait_micros: AtomicU64,
+}
+
+impl ServerStats {
+    fn snapshot(&self, config: &FileServerConfig) -> FileServerStats {
+        let completed = self.completed.load(Ordering::Relaxed);
+        let total_wait_micros = self.total_wait_micros.load(Ordering::Relaxed);
+        FileServerStats {
+            worker_count: config.worker_count,
+            queue_capacity: config.queue_capacity,
+            queued: self.queued.load(Ordering::Relaxed),
+            peak_queued: self.peak_queued.load(Ordering::Relaxed),
+            active: self.active.load(Ordering::Relaxed),
+            completed,
+            rejected: self.rejected.load(Ordering::Relaxed),
+            average_wait_ms: if completed == 0 {
+                0.0
+            } else {
+                total_wait_micros as f64 / completed as f64 / 1000.0
+            },
         }
     }
 }
 
-fn handle_request(url: &str, base_path: Arc<String>) -> Response<std::io::Cursor<Vec<u8>>> {
-    // 移除查询参数
-    let path_part_encoded = url.split('?').next().unwrap_or(url);
+/// 请求队列统计信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileServerStats {
+    pub worker_count: usize,
+    pub queue_capacity: usize,
+    /// 当前排队中的请求数
+    pub queued: usize,
+    /// 启动以来的最大排队数
+    pub peak_queued: usize,
+    /// 正在处理中的请求数
+    pub active: usize,
+    /// 已处理完成的请求数
+    pub completed: u64,
+    /// 因队列已满被拒绝的请求数
+    pub rejected: u64,
+    /// 请求在队列中的平均等待时间（毫秒）
+    pub average_wait_ms: f64,
+}
+
+/// 正在运行的文件服务器
+pub struct FileServerHandle {
+    server: Arc<Server>,
+    /// 接收线程；工作线程不保存句柄，停止时不等待它们
+    acceptor: JoinHandle<()>,
+    port: u16,
+    config: Arc<FileServerConfig>,
+    stats: Arc<ServerStats>,
+}
+
+impl FileServerHandle {
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// 不含令牌的服务器地址，可以安全地写入日志
+    pub fn origin(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// webview 使用的基础 URL，包含路径形式的会话令牌
+    pub fn base_url(&self) -> String {
+        match &self.config.access_token {
+            Some(token) => format!("{}{}{}", self.origin(), auth::TOKEN_PATH_PREFIX, token),
+            None => self.origin(),
+        }
+    }
+
+    pub fn stats(&self) -> FileServerStats {
+        self.stats.snapshot(&self.config)
+    }
+
+    /// 停止服务器：解除 `incoming_requests` 的阻塞并等待接收线程退出，随后释放端口
+    ///
+    /// 工作线程可能正在向暂停播放的视频连接写入而长时间阻塞，因此不等待它们；
+    /// 队列关闭后它们在发送完手头的响应时自行退出
+    pub fn stop(self) {
+        let origin = self.origin();
+        self.server.unblock();
+        if self.acceptor.join().is_err() {
+            log::error!("文件服务器线程异常退出");
+        }
+        log::info!("文件服务器已停止: {}", origin);
+    }
+}
+
+/// 文件服务器运行状态
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileServerStatus {
+    pub running: bool,
+    pub transport: Option<Transport>,
+    pub port: Option<u16>,
+    pub base_url: Option<String>,
+    pub base_path: Option<String>,
+    pub stats: Option<FileServerStats>,
+}
+
+/// 启动后返回给前端的访问信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalServerInfo {
+    /// 资源的基础 URL，HTTP 模式下已包含路径形式的令牌，直接拼接相对路径即可
+    pub base_url: String,
+    /// 会话令牌，也可以通过 `X-WebGAL-Token` 请求头或 `token` 查询参数提供
+    pub token: Option<String>,
+    pub transport: Transport,
+}
+
+/// 挂载点信息
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MountInfo {
+    pub name: String,
+    pub path: String,
+    /// 访问该挂载点时使用的 URL 前缀，如 `/library`
+    pub url_prefix: String,
+}
+
+/// 由 Tauri `manage()` 托管的文件服务器状态，同一时间最多运行一个服务器
+///
+/// 挂载点、Origin 允许列表和访问日志保存在这里而不是服务器上，因此切换游戏文件夹重启服务器后依然有效
+pub struct FileServerState {
+    handle: Mutex<Option<FileServerHandle>>,
+    mounts: MountTable,
+    allowed_origins: OriginList,
+    access_log: Arc<AccessLog>,
+    /// `webgal-asset://` 协议使用的配置，未设置时协议返回 503
+    protocol_config: RwLock<Option<FileServerConfig>>,
+    change_hub: Arc<ChangeHub>,
+    /// 监视当前根目录的文件变化，随服务器一起启动和停止
+    watcher: Mutex<Option<AssetWatcher>>,
+}
+
+impl Default for FileServerState {
+    fn default() -> Self {
+        Self {
+            handle: Mutex::default(),
+            mounts: MountTable::default(),
+            allowed_origins: cors::default_origins(),
+            access_log: Arc::default(),
+            protocol_config: RwLock::default(),
+            change_hub: Arc::default(),
+            watcher: Mutex::default(),
+        }
+    }
+}
+
+impl FileServerState {
+    /// 开始提供资源，返回 webview 应使用的基础 URL 和会话令牌
+    ///
+    /// 资源协议总是指向最新的配置；选择 HTTP 时还会用新的随机令牌启动服务器，
+    /// 如果已有服务器在运行则先将其停止
+    pub fn start(
+        &self,
+        mut config: FileServerConfig,
+        transport: Transport,
+    ) -> Result<LocalServerInfo, String> {
+        config.mounts = Arc::clone(&self.mounts);
+        config.allowed_origins = Arc::clone(&self.allowed_origins);
+        config.access_log = Arc::clone(&self.access_log);
+        config.change_hub = Arc::clone(&self.change_hub);
+
+        // 停止旧服务器时不持有锁，其他命令不会被阻塞
+        if let Some(previous) = self.take_handle()? {
+            previous.stop();
+        }
+        self.restart_watcher(Some(&config.base_path));
+
+        *self
+            .protocol_config
+            .write()
+            .map_err(|e| format!("获取服务器状态失败: {}", e))? = Some(config.clone());
+
+        if transport == Transport::Protocol {
+            return Ok(LocalServerInfo {
+                base_url: protocol_base_url(),
+                token: None,
+                transport,
+            });
+        }
+
+        // 端口对本机所有进程可见，因此 HTTP 模式总是要求令牌
+        let token = auth::generate_token();
+        config.access_token = Some(token.clone());
+
+        let port = find_available_port(8000).ok_or("找不到可用端口")?;
+        let server = start_file_server(port, config).ok_or("启动文件服务器失败")?;
+        let base_url = server.base_url();
+        let replaced = self
+            .handle
+            .lock()
+            .map_err(|e| format!("获取服务器状态失败: {}", e))?
+            .replace(server);
+        // 另一次并发的启动已经装入了服务器
+        if let Some(replaced) = replaced {
+            replaced.stop();
+        }
+        Ok(LocalServerInfo {
+            base_url,
+            token: Some(token),
+            transport,
+        })
+    }
+
+    /// 停止提供资源，返回之前是否在运行
+    pub fn stop(&self) -> Result<bool, String> {
+        let handle = self.take_handle()?;
+        let protocol_config = self
+            .protocol_config
+            .write()
+            .map_err(|e| format!("获取服务器状态失败: {}", e))?
+            .take();
+        self.restart_watcher(None);
 
-    println!("🔍 请求 URL: {}", url);
-    println!("🔍 路径部分（编码前）: {}", path_part_encoded);
+        match handle {
+            Some(server) => {
+                server.stop();
+                Ok(true)
+            }
+            None => Ok(protocol_config.is_some()),
+        }
+    }
 
-    // 解码 URL 编码的路径
-    let path_part = urlencoding::decode(path_part_encoded)
-        .map(|s| s.to_string())
-        .unwrap_or_else(|_| path_part_encoded.to_string());
+    fn take_handle(&self) -> Result<Option<FileServerHandle>, String> {
+        Ok(self
+            .handle
+            .lock()
+            .map_err(|e| format!("获取服务器状态失败: {}", e))?
+            .take())
+    }
 
-    println!("🔍 路径部分（解码后）: {}", path_part);
-    println!("🔍 基础路径: {}", *base_path);
+    /// 停止当前的文件监视并断开事件流连接，`base_path` 不为空时开始监视新的根目录
+    ///
+    /// 建立递归监视和停止监视线程都可能较慢，调用方不应持有 `handle` 锁，也不应在主线程中调用。
+    /// 监视失败（如根目录所在的文件系统不支持通知）不影响提供资源，只记录警告
+    fn restart_watcher(&self, base_path: Option<&str>) {
+        let Ok(mut watcher) = self.watcher.lock() else {
+            return;
+        };
+        if let Some(previous) = watcher.take() {
+            previous.stop();
+        }
+        self.change_hub.close_subscribers();
 
-    // 构造完整文件路径
-    let file_path = Path::new(&*base_path).join(path_part.trim_start_matches('/'));
+        *watcher = base_path.and_then(|base_path| {
+            AssetWatcher::start(base_path, Arc::clone(&self.change_hub))
+                .map_err(|e| log::warn!("{}", e))
+                .ok()
+        });
+    }
 
-    println!("🔍 完整文件路径: {:?}", file_path);
+    /// 设置资源变化的应用内监听器，服务器重启后依然有效
+    pub fn set_change_listener(&self, listener: impl Fn(&AssetChanges) + Send + Sync + 'static) {
+        self.change_hub.set_listener(listener);
+    }
 
-    // 检查文件是否存在
-    if !file_path.exists() || !file_path.is_file() {
-        return add_cors_headers(Response::from_string("File not found").with_status_code(404));
+    /// 订阅 `base_path` 中的文件变化，路径相对于 `base_path`；当前没有在监视该目录时返回 `None`
+    ///
+    /// 服务器停止或切换根目录时订阅随之断开
+    pub fn subscribe_changes(&self, base_path: &Path) -> Option<Receiver<Arc<AssetChanges>>> {
+        let watcher = self.watcher.lock().ok()?;
+        watcher
+            .as_ref()
+            .filter(|watcher| watcher.watches(base_path))?;
+        Some(self.change_hub.subscribe())
     }
 
-    // 读取文件
-    match fs::read(&file_path) {
-        Ok(content) => {
-            // 猜测 MIME 类型
-            let mime_type = guess_mime_type(&file_path);
+    /// 资源协议当前使用的配置
+    pub fn protocol_config(&self) -> Option<FileServerConfig> {
+        self.protocol_config
+            .read()
+            .ok()
+            .and_then(|config| config.clone())
+    }
 
-            // 构造响应
-            let mut response = Response::from_data(content).with_status_code(200);
+    /// 添加或替换命名挂载点，运行中的服务器立即生效
+    pub fn add_mount(&self, name: String, path: String) -> Result<MountInfo, String> {
+        validate_mount_name(&name)?;
+        let dir = Path::new(&path);
+        if !dir.is_dir() && archive::split_archive_path(dir).is_none() {
+            return Err(format!("路径不是目录或 zip 文件: {}", path));
+        }
+
+        let mut mounts = self
+            .mounts
+            .write()
+            .map_err(|e| format!("获取挂载点失败: {}", e))?;
+        mounts.insert(name.clone(), path.clone());
+        Ok(MountInfo {
+            url_prefix: format!("/{}", name),
+            name,
+            path,
+        })
+    }
+
+    /// 移除命名挂载点，返回该挂载点之前是否存在
+    pub fn remove_mount(&self, name: &str) -> Result<bool, String> {
+        let mut mounts = self
+            .mounts
+            .write()
+            .map_err(|e| format!("获取挂载点失败: {}", e))?;
+        Ok(mounts.remove(name).is_some())
+    }
+
+    pub fn list_mounts(&self) -> Result<Vec<MountInfo>, String> {
+        let mounts = self
+            .mounts
+            .read()
+            .map_err(|e| format!("获取挂载点失败: {}", e))?;
+        Ok(mounts
+            .iter()
+            .map(|(name, path)| MountInfo {
+                name: name.clone(),
+                path: path.clone(),
+                url_prefix: format!("/{}", name),
+            })
+            .collect())
+    }
+
+    /// 替换 Origin 允许列表，运行中的服务器和资源协议立即生效
+    pub fn set_allowed_origins(&self, origins: Vec<String>) -> Result<Vec<String>, String> {
+        let origins: Vec<String> = origins
+            .into_iter()
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        let mut allowed = self
+            .allowed_origins
+            .write()
+            .map_err(|e| format!("获取 Origin 列表失败: {}", e))?;
+        *allowed = origins.clone();
+        Ok(origins)
+    }
 
-            // 添加 Content-Type header
-            if let Ok(header) =
-                tiny_http::Header::from_bytes(&b"Content-Type"[..], mime_type.as_bytes())
-            {
-                response = response.with_header(header);
+    pub fn allowed_origins(&self) -> Result<Vec<String>, String> {
+        self.allowed_origins
+            .read()
+            .map(|allowed| allowed.clone())
+            .map_err(|e| format!("获取 Origin 列表失败: {}", e))
+    }
+
+    /// 最近的访问记录，按时间从旧到新排列
+    pub fn recent_access_log(&self, limit: usize) -> Vec<AccessLogEntry> {
+        self.access_log.recent(limit)
+    }
+
+    pub fn clear_access_log(&self) {
+        self.access_log.clear();
+    }
+
+    /// 设置成功请求写入日志时使用的级别，返回规范化后的级别名
+    pub fn set_access_log_level(&self, level: &str) -> Result<String, String> {
+        self.access_log
+            .set_success_level(level)
+            .map(|level| level.to_string().to_lowercase())
+    }
+
+    pub fn status(&self) -> Result<FileServerStatus, String> {
+        let handle = self
+            .handle
+            .lock()
+            .map_err(|e| format!("获取服务器状态失败: {}", e))?;
+        Ok(match (handle.as_ref(), self.protocol_config()) {
+            (Some(server), _) => FileServerStatus {
+                running: true,
+                transport: Some(Transport::Http),
+                port: Some(server.port()),
+                base_url: Some(server.base_url()),
+                base_path: Some(server.config.base_path.clone()),
+                stats: Some(server.stats()),
+            },
+            (None, Some(config)) => FileServerStatus {
+                running: true,
+                transport: Some(Transport::Protocol),
+                port: None,
+                base_url: Some(protocol_base_url()),
+                base_path: Some(config.base_path),
+                stats: None,
+            },
+            (None, None) => FileServerStatus {
+                running: false,
+                transport: None,
+                port: None,
+                base_url: None,
+                base_path: None,
+                stats: None,
+            },
+        })
+    }
+}
+
+/// 挂载点名称只能包含字母、数字、`-` 和 `_`；`__` 开头的名称保留给内部接口
+fn validate_mount_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.starts_with("__")
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("无效的挂载点名称: {}", name));
+    }
+    Ok(())
+}
+
+fn find_available_port(start_port: u16) -> Option<u16> {
+    use std::net::TcpListener;
+    for port in start_port..=start_port + 100 {
+        if TcpListener::bind(format!("127.0.0.1:{}", port)).is_ok() {
+            return Some(port);
+        }
+    }
+    None
+}
+
+/// 启动 HTTP 文件服务器，`worker_count` 超出范围时按 1 到 [`MAX_WORKER_COUNT`] 处理
+pub fn start_file_server(port: u16, mut config: FileServerConfig) -> Option<FileServerHandle> {
+    let address = format!("127.0.0.1:{}", port);
+    config.worker_count = config.worker_count.clamp(1, MAX_WORKER_COUNT);
+
+    match Server::http(&address) {
+        Ok(server) => {
+            log::info!(
+                "文件服务器已启动: http://{} ({} 个工作线程)",
+                address,
+                config.worker_count
+            );
+            let server = Arc::new(server);
+            let config = Arc::new(config);
+            let stats = Arc::new(ServerStats::default());
+
+            // 接收线程把请求放入有界队列，由工作线程并行处理
+            let (sender, receiver) =
+                mpsc::sync_channel::<(Request, Instant)>(config.queue_capacity);
+            let receiver = Arc::new(Mutex::new(receiver));
+
+            for _ in 0..config.worker_count {
+                let receiver = Arc::clone(&receiver);
+                let config = Arc::clone(&config);
+                let stats = Arc::clone(&stats);
+                std::thread::spawn(move || worker_loop(&receiver, &config, &stats));
             }
 
-            // 添加所有 CORS headers
-            add_cors_headers(response)
+            let acceptor = {
+                let server = Arc::clone(&server);
+                let config = Arc::clone(&config);
+                let stats = Arc::clone(&stats);
+                std::thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        // 先计数再入队，避免工作线程先于计数取走请求
+                        let queued = stats.queued.fetch_add(1, Ordering::Relaxed) + 1;
+                        stats.peak_queued.fetch_max(queued, Ordering::Relaxed);
+
+                        match sender.try_send((request, Instant::now())) {
+                            Ok(()) => {}
+                            Err(TrySendError::Full((request, _)))
+                            | Err(TrySendError::Disconnected((request, _))) => {
+                                stats.queued.fetch_sub(1, Ordering::Relaxed);
+                                stats.rejected.fetch_add(1, Ordering::Relaxed);
+                                let asset_request = to_asset_request(&request);
+                                let response = cors::add_cors_headers(
+                                    AssetResponse::text(503, "Service Unavailable"),
+                                    asset_request.header("Origin"),
+                                    &config.allowed_origins,
+                                );
+                                config.access_log.record(AccessLogEntry::new(
+                                    &asset_request.method,
+                                    &auth::strip_token(&asset_request).0,
+                                    response.status,
+                                    response.body.len(),
+                                    Duration::ZERO,
+                                    asset_request.header("Range"),
+                                ));
+                                // 客户端不读取时写入会阻塞，交给独立线程发送，不占用接收线程
+                                std::thread::spawn(move || {
+                                    if let Err(e) =
+                                        request.respond(into_tiny_http_response(response))
+                                    {
+                                        log::error!("发送响应失败: {}", e);
+                                    }
+                                });
+                            }
+                        }
+                    }
+                    // sender 在这里被丢弃，工作线程随之退出
+                })
+            };
+
+            Some(FileServerHandle {
+                server,
+                acceptor,
+                port,
+                config,
+                stats,
+            })
         }
         Err(e) => {
-            eprintln!("读取文件失败: {}", e);
-            add_cors_headers(
-                Response::from_string(format!("Internal Server Error: {}", e))
-                    .with_status_code(500),
-            )
+            log::error!("启动文件服务器失败: {}", e);
+            None
+        }
+    }
+}
+
+/// 工作线程：从队列中取出请求并处理，直到接收线程退出
+fn worker_loop(
+    receiver: &Mutex<Receiver<(Request, Instant)>>,
+    config: &FileServerConfig,
+    stats: &ServerStats,
+) {
+    loop {
+        let next = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok((request, enqueued_at)) = next else {
+            return;
+        };
+
+        stats.queued.fetch_sub(1, Ordering::Relaxed);
+        stats.active.fetch_add(1, Ordering::Relaxed);
+        stats
+            .total_wait_micros
+            .fetch_add(enqueued_at.elapsed().as_micros() as u64, Ordering::Relaxed);
+
+        // 处理请求；事件流会一直占用连接，交给独立线程发送，不占用工作线程
+        let response = serve_asset(&to_asset_request(&request), config);
+        if let AssetBody::Live(_) = response.body {
+            std::thread::spawn(move || send_live_response(request, response));
+        } else if let Err(e) = request.respond(into_tiny_http_response(response)) {
+            log::error!("发送响应失败: {}", e);
         }
+
+        stats.active.fetch_sub(1, Ordering::Relaxed);
+        stats.completed.fetch_add(1, Ordering::Relaxed);
     }
 }
 
-fn add_cors_headers(
-    mut response: Response<std::io::Cursor<Vec<u8>>>,
-) -> Response<std::io::Cursor<Vec<u8>>> {
-    if let Ok(header) = tiny_http::Header::from_bytes(&b"Access-Control-Allow-Origin"[..], b"*") {
-        response = response.with_header(header);
+fn to_asset_request(request: &Request) -> AssetRequest {
+    AssetRequest {
+        method: request.method().as_str().to_uppercase(),
+        url: request.url().to_string(),
+        headers: request
+            .headers()
+            .iter()
+            .map(|h| (h.field.as_str().to_string(), h.value.as_str().to_string()))
+            .collect(),
     }
-    if let Ok(header) =
-        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Methods"[..], b"GET, POST, OPTIONS")
-    {
-        response = response.with_header(header);
+}
+
+/// 把通用响应转换为 tiny_http 响应，文件内容保持流式发送
+fn into_tiny_http_response(response: AssetResponse) -> ResponseBox {
+    let headers = response
+        .headers
+        .iter()
+        .filter_map(|(field, value)| Header::from_bytes(field.as_bytes(), value.as_bytes()).ok())
+        .collect();
+    let len = match response.body {
+        AssetBody::Live(_) => None,
+        ref body => Some(body.len() as usize),
+    };
+    let reader: Box<dyn Read + Send> = match response.body {
+        AssetBody::Empty | AssetBody::Head(_) => Box::new(std::io::empty()),
+        AssetBody::Bytes(bytes) => Box::new(Cursor::new(bytes)),
+        AssetBody::Shared(bytes) => Box::new(Cursor::new(bytes)),
+        AssetBody::Stream { reader, .. } => reader,
+        AssetBody::Live(reader) => reader,
+    };
+    Response::new(StatusCode(response.status), headers, reader, len, None)
+}
+
+/// 直接写入连接发送长连接响应，每读到一块数据就立即刷新
+///
+/// tiny_http 的分块编码会先缓冲数据，事件会被延迟，因此这里自行写出响应头，
+/// 不使用 Content-Length 和分块编码，响应体在连接关闭时结束
+fn send_live_response(request: Request, response: AssetResponse) {
+    let AssetBody::Live(mut reader) = response.body else {
+        return;
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\n",
+        response.status,
+        StatusCode(response.status).default_reason_phrase()
+    );
+    for (field, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", field, value));
     }
-    if let Ok(header) =
-        tiny_http::Header::from_bytes(&b"Access-Control-Allow-Headers"[..], b"Content-Type")
-    {
-        response = response.with_header(header);
-    }
-    response
-}
-
-fn guess_mime_type(path: &Path) -> String {
-    if let Some(ext) = path.extension() {
-        match ext.to_string_lossy().to_lowercase().as_str() {
-            "png" => "image/png",
-            "jpg" | "jpeg" => "image/jpeg",
-            "gif" => "image/gif",
-            "webp" => "image/webp",
-            "bmp" => "image/bmp",
-            "webm" => "video/webm",
-            "json" => "application/json",
-            "jsonl" => "application/x-ndjson",
-            "moc" => "application/octet-stream",
-            "moc3" => "application/octet-stream",
-            "physics" | "physics3" => "application/octet-stream",
-            "motion" | "motion3" => "application/octet-stream",
-            "expression" | "exp3" => "application/octet-stream",
-            "mtn" => "application/octet-stream",
-            "exp" => "application/octet-stream",
-            _ => "application/octet-stream",
-        }
-        .to_string()
-    } else {
-        "application/octet-stream".to_string()
+    head.push_str("Connection: close\r\n\r\n");
+
+    let mut writer = request.into_writer();
+    if let Err(e) = write_live_body(&mut writer, head.as_bytes(), &mut reader) {
+        log::debug!("长连接已关闭: {}", e);
+    }
+}
+
+fn write_live_body(
+    writer: &mut dyn Write,
+    head: &[u8],
+    reader: &mut dyn Read,
+) -> std::io::Result<()> {
+    writer.write_all(head)?;
+    writer.flush()?;
+    let mut buffer = [0; 8192];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buffer[..read])?;
+        writer.flush()?;
     }
 }
@@ -0,0 +1,143 @@
+use log::{Level, LevelFilter};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 访问日志使用的 log target，可以在日志配置中单独过滤
+pub const ACCESS_LOG_TARGET: &str = "webgal::access";
+
+/// 内存中保留的最近访问记录条数
+pub const DEFAULT_ACCESS_LOG_CAPACITY: usize = 500;
+
+/// 一条访问记录，URL 中的访问令牌已被去掉
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessLogEntry {
+    /// 请求完成时间（Unix 毫秒）
+    pub timestamp: u64,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// 响应体字节数，HEAD 和 304 为 0
+    pub bytes: u64,
+    /// 处理耗时（毫秒），不含发送响应体的时间
+    pub latency_ms: f64,
+    /// 请求携带的 Range 头
+    pub range: Option<String>,
+}
+
+impl AccessLogEntry {
+    pub fn new(
+        method: &str,
+        path: &str,
+        status: u16,
+        bytes: u64,
+        latency: Duration,
+        range: Option<&str>,
+    ) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            method: method.to_string(),
+            path: path.to_string(),
+            status,
+            bytes,
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            range: range.map(str::to_string),
+        }
+    }
+
+    /// 记录使用的日志级别：5xx 为 Error，4xx 为 Warn，其余使用配置的级别
+    fn level(&self, success_level: LevelFilter) -> Option<Level> {
+        match self.status {
+            500.. => Some(Level::Error),
+            400..=499 => Some(Level::Warn),
+            _ => success_level.to_level(),
+        }
+    }
+}
+
+/// 访问日志：写入 `log` facade，同时在内存中保留最近的记录供界面查看
+#[derive(Debug)]
+pub struct AccessLog {
+    entries: Mutex<VecDeque<AccessLogEntry>>,
+    capacity: usize,
+    /// 成功请求（非 4xx/5xx）写入日志时使用的级别，`Off` 表示只保留在内存中
+    success_level: RwLock<LevelFilter>,
+}
+
+impl Default for AccessLog {
+    fn default() -> Self {
+        Self {
+            entries: Mutex::default(),
+            capacity: DEFAULT_ACCESS_LOG_CAPACITY,
+            success_level: RwLock::new(LevelFilter::Info),
+        }
+    }
+}
+
+impl AccessLog {
+    pub fn record(&self, entry: AccessLogEntry) {
+        let success_level = self.success_level();
+        if let Some(level) = entry.level(success_level) {
+            log::log!(
+                target: ACCESS_LOG_TARGET,
+                level,
+                "method={} path={} status={} bytes={} latency_ms={:.2} range={}",
+                entry.method,
+                entry.path,
+                entry.status,
+                entry.bytes,
+                entry.latency_ms,
+                entry.range.as_deref().unwrap_or("-")
+            );
+        }
+
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() >= self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    /// 最近的 `limit` 条记录，按时间从旧到新排列
+    pub fn recent(&self, limit: usize) -> Vec<AccessLogEntry> {
+        match self.entries.lock() {
+            Ok(entries) => {
+                let skip = entries.len().saturating_sub(limit);
+                entries.iter().skip(skip).cloned().collect()
+            }
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+
+    pub fn success_level(&self) -> LevelFilter {
+        self.success_level
+            .read()
+            .map(|level| *level)
+            .unwrap_or(LevelFilter::Info)
+    }
+
+    /// 设置成功请求的日志级别，接受 `off`、`error`、`warn`、`info`、`debug`、`trace`
+    pub fn set_success_level(&self, level: &str) -> Result<LevelFilter, String> {
+        let level: LevelFilter = level
+            .parse()
+            .map_err(|_| format!("无效的日志级别: {}", level))?;
+        let mut current = self
+            .success_level
+            .write()
+            .map_err(|e| format!("获取日志级别失败: {}", e))?;
+        *current = level;
+        Ok(level)
+    }
+}
@@ -0,0 +1,430 @@
+use flate2::read::DeflateDecoder;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use zip::{CompressionMethod, ZipArchive};
+
+/// 读取 zip 条目时每次从磁盘读取的块大小
+const ARCHIVE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 按声明的大小预分配缓冲区的上限；中央目录中的大小不可信，超过时边读边扩展
+pub(crate) const MAX_PREALLOCATION: u64 = 1024 * 1024;
+
+/// WebGAL 的资源目录名；zip 只包含这一个顶层目录时不会被当作外层包装目录去掉
+const GAME_DIR: &str = "game";
+
+/// 判断路径是否指向一个 zip 文件
+pub fn is_archive(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        && path.is_file()
+}
+
+/// 在路径中查找 zip 文件，返回 zip 路径和包内的相对路径
+///
+/// 例如 `D:/packs/game.zip/game/figure` 会得到 `(D:/packs/game.zip, "game/figure")`
+pub fn split_archive_path(path: &Path) -> Option<(PathBuf, String)> {
+    // 绝大多数路径不包含 zip，先做字符串检查，避免对每一级目录做文件系统调用
+    if !path.to_string_lossy().to_lowercase().contains(".zip") {
+        return None;
+    }
+    let mut ancestors: Vec<&Path> = path.ancestors().collect();
+    ancestors.reverse();
+    let archive = ancestors
+        .into_iter()
+        .find(|ancestor| is_archive(ancestor))?;
+    let inner = path.strip_prefix(archive).ok()?;
+    Some((
+        archive.to_path_buf(),
+        normalize_entry_name(&inner.to_string_lossy())?,
+    ))
+}
+
+/// 把 zip 条目名或包内路径规范化为 `a/b/c` 形式，`..` 超出根目录时返回 `None`
+pub fn normalize_entry_name(name: &str) -> Option<String> {
+    let mut segments = Vec::new();
+    for segment in name.split(['/', '\\']) {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// zip 中的一个文件条目
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// 解压后的大小
+    pub size: u64,
+    /// 解压后数据的 CRC32，用于判断条目内容是否变化
+    pub crc32: u32,
+    data_start: u64,
+    compressed_size: u64,
+    deflated: bool,
+}
+
+/// zip 的中央目录索引，只在打开时解析一次
+#[derive(Debug)]
+pub struct ArchiveIndex {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+    /// 规范化后的条目路径 -> 条目
+    entries: BTreeMap<String, ArchiveEntry>,
+    /// 所有目录（包括只由文件路径隐含的目录），根目录为空字符串
+    dirs: BTreeSet<String>,
+}
+
+impl ArchiveIndex {
+    /// 读取 zip 的中央目录
+    ///
+    /// 只支持未压缩和 Deflate 条目，加密或其他压缩方式的条目会被忽略；
+    /// 如果所有文件都位于同一个顶层目录（打包整个文件夹时常见）且该目录不是 `game`，
+    /// 则把这一层目录当作根目录
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let file = fs::File::open(path)?;
+        let mut archive = ZipArchive::new(BufReader::new(file)).map_err(io::Error::other)?;
+
+        let mut entries = BTreeMap::new();
+        for i in 0..archive.len() {
+            let file = archive.by_index_raw(i).map_err(io::Error::other)?;
+            if file.is_dir() || file.encrypted() {
+                continue;
+            }
+            let deflated = match file.compression() {
+                CompressionMethod::Stored => false,
+                CompressionMethod::Deflated => true,
+                other => {
+                    log::debug!("跳过不支持的压缩方式 {:?}: {}", other, file.name());
+                    continue;
+                }
+            };
+            let Some(name) = normalize_entry_name(file.name()).filter(|name| !name.is_empty())
+            else {
+                continue;
+            };
+            entries.insert(
+                name,
+                ArchiveEntry {
+                    size: file.size(),
+                    crc32: file.crc32(),
+                    data_start: file.data_start(),
+                    compressed_size: file.compressed_size(),
+                    deflated,
+                },
+            );
+        }
+
+        let entries = strip_wrapper_dir(entries);
+        let mut dirs = BTreeSet::from([String::new()]);
+        for name in entries.keys() {
+            let mut end = 0;
+            while let Some(pos) = name[end..].find('/') {
+                end += pos;
+                dirs.insert(name[..end].to_string());
+                end += 1;
+            }
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            entries,
+            dirs,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// zip 文件本身的修改时间，包内所有条目都使用这个时间计算 ETag
+    pub fn modified(&self) -> Option<SystemTime> {
+        self.modified
+    }
+
+    pub fn entry(&self, name: &str) -> Option<&ArchiveEntry> {
+        self.entries.get(name)
+    }
+
+    /// 按路径顺序遍历所有文件条目
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &ArchiveEntry)> {
+        self.entries
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry))
+    }
+
+    pub fn is_dir(&self, name: &str) -> bool {
+        self.dirs.contains(name)
+    }
+
+    /// 列出目录下的直接子项（文件和子目录），返回完整的条目路径
+    pub fn children(&self, dir: &str) -> Vec<String> {
+        let prefix = if dir.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", dir)
+        };
+        let is_child = |name: &&String| {
+            name.strip_prefix(&prefix)
+                .is_some_and(|rest| !rest.is_empty() && !rest.contains('/'))
+        };
+
+        let files = self
+            .entries
+            .range(prefix.clone()..)
+            .map(|(name, _)| name)
+            .take_while(|name| name.starts_with(&prefix))
+            .filter(is_child);
+        let dirs = self
+            .dirs
+            .range(prefix.clone()..)
+            .take_while(|name| name.starts_with(&prefix))
+            .filter(is_child);
+        files.chain(dirs).cloned().collect()
+    }
+
+    /// 打开条目并跳过前 `start` 字节，返回最多读取 `len` 字节的流
+    ///
+    /// 未压缩的条目直接定位到对应偏移；Deflate 条目边读边解压，
+    /// Range 请求需要先解压并丢弃 `start` 之前的数据
+    pub fn open_entry(
+        &self,
+        entry: &ArchiveEntry,
+        start: u64,
+        len: u64,
+    ) -> io::Result<Box<dyn Read + Send>> {
+        let mut file = fs::File::open(&self.path)?;
+        if !entry.deflated {
+            file.seek(SeekFrom::Start(entry.data_start + start))?;
+            return Ok(Box::new(
+                BufReader::with_capacity(ARCHIVE_CHUNK_SIZE, file).take(len),
+            ));
+        }
+
+        file.seek(SeekFrom::Start(entry.data_start))?;
+        let compressed =
+            BufReader::with_capacity(ARCHIVE_CHUNK_SIZE, file).take(entry.compressed_size);
+        let mut decoder = DeflateDecoder::new(compressed);
+        io::copy(&mut (&mut decoder).take(start), &mut io::sink())?;
+        Ok(Box::new(decoder.take(len)))
+    }
+
+    /// 读取整个条目，解压后的数据超过声明的大小时返回错误
+    pub fn read_entry(&self, entry: &ArchiveEntry) -> io::Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(entry.size.min(MAX_PREALLOCATION) as usize);
+        // 未压缩的条目之后是下一条记录，只对压缩条目多读一个字节检查实际大小
+        let limit = if entry.deflated {
+            entry.size.saturating_add(1)
+        } else {
+            entry.size
+        };
+        self.open_entry(entry, 0, limit)?.read_to_end(&mut data)?;
+        if data.len() as u64 > entry.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "zip 条目解压后的大小超过声明的大小",
+            ));
+        }
+        Ok(data)
+    }
+}
+
+/// 所有条目共享同一个顶层目录时去掉这一层
+fn strip_wrapper_dir(entries: BTreeMap<String, ArchiveEntry>) -> BTreeMap<String, ArchiveEntry> {
+    let mut roots = entries
+        .keys()
+        .map(|name| name.split_once('/').map(|(root, _)| root));
+    let Some(Some(root)) = roots.next() else {
+        return entries;
+    };
+    if root == GAME_DIR || !roots.all(|other| other == Some(root)) {
+        return entries;
+    }
+
+    let prefix = format!("{}/", root);
+    entries
+        .into_iter()
+        .filter_map(|(name, entry)| Some((name.strip_prefix(&prefix)?.to_string(), entry)))
+        .collect()
+}
+
+/// zip 索引缓存，zip 文件的修改时间或大小变化后重新解析
+#[derive(Debug, Default)]
+pub struct ArchiveCache {
+    indexes: Mutex<HashMap<PathBuf, Arc<ArchiveIndex>>>,
+}
+
+impl ArchiveCache {
+    pub fn get(&self, path: &Path) -> io::Result<Arc<ArchiveIndex>> {
+        let metadata = fs::metadata(path)?;
+        let (modified, len) = (metadata.modified().ok(), metadata.len());
+
+        if let Ok(indexes) = self.indexes.lock() {
+            if let Some(index) = indexes.get(path) {
+                if index.modified == modified && index.len == len {
+                    return Ok(Arc::clone(index));
+                }
+            }
+        }
+
+        // 解析在锁外进行，大型 zip 不会阻塞其他请求
+        let index = Arc::new(ArchiveIndex::open(path)?);
+        if let Ok(mut indexes) = self.indexes.lock() {
+            indexes.insert(path.to_path_buf(), Arc::clone(&index));
+        }
+        Ok(index)
+    }
+}
+
+/// 扫描素材目录时使用的只读文件系统，路径可以穿过 zip 文件
+///
+/// zip 内的路径写作 `<zip 路径>/<包内路径>`，与普通目录下的路径形式一致，
+/// 因此目录遍历代码不需要区分两者
+pub enum AssetFs {
+    Disk,
+    Archive(Arc<ArchiveIndex>),
+}
+
+impl AssetFs {
+    /// 根据要扫描的路径选择文件系统
+    pub fn for_path(path: &Path) -> io::Result<Self> {
+        match split_archive_path(path) {
+            Some((archive, _)) => Ok(Self::Archive(Arc::new(ArchiveIndex::open(&archive)?))),
+            None => Ok(Self::Disk),
+        }
+    }
+
+    /// zip 内的路径转换为条目名；不在 zip 内时返回 `None`
+    fn entry_name(index: &ArchiveIndex, path: &Path) -> Option<String> {
+        let inner = path.strip_prefix(index.path()).ok()?;
+        normalize_entry_name(&inner.to_string_lossy())
+    }
+
+    pub fn exists(&self, path: &Path) -> bool {
+        self.is_dir(path) || self.is_file(path)
+    }
+
+    pub fn is_dir(&self, path: &Path) -> bool {
+        match self {
+            Self::Disk => path.is_dir(),
+            Self::Archive(index) => {
+                Self::entry_name(index, path).is_some_and(|name| index.is_dir(&name))
+            }
+        }
+    }
+
+    pub fn is_file(&self, path: &Path) -> bool {
+        match self {
+            Self::Disk => path.is_file(),
+            Self::Archive(index) => {
+                Self::entry_name(index, path).is_some_and(|name| index.entry(&name).is_some())
+            }
+        }
+    }
+
+    /// 列出目录下的直接子项
+    pub fn read_dir(&self, path: &Path) -> io::Result<Vec<PathBuf>> {
+        match self {
+            Self::Disk => fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect(),
+            Self::Archive(index) => match Self::entry_name(index, path) {
+                Some(dir) if index.is_dir(&dir) => Ok(index
+                    .children(&dir)
+                    .into_iter()
+                    .map(|name| index.path().join(name))
+                    .collect()),
+                _ => Err(io::Error::new(io::ErrorKind::NotFound, "目录不存在")),
+            },
+        }
+    }
+
+    /// 文件大小和修改时间；zip 内的文件使用 zip 本身的修改时间
+    pub fn stat(&self, path: &Path) -> io::Result<(u64, Option<SystemTime>)> {
+        match self {
+            Self::Disk => {
+                let metadata = fs::metadata(path)?;
+                Ok((metadata.len(), metadata.modified().ok()))
+            }
+            Self::Archive(index) => {
+                let entry = Self::archive_entry(index, path)?;
+                Ok((entry.size, index.modified()))
+            }
+        }
+    }
+
+    pub fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Disk => fs::read(path),
+            Self::Archive(index) => index.read_entry(&Self::archive_entry(index, path)?),
+        }
+    }
+
+    pub fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn archive_entry(index: &ArchiveIndex, path: &Path) -> io::Result<ArchiveEntry> {
+        Self::entry_name(index, path)
+            .and_then(|name| index.entry(&name).cloned())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "文件不存在"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    /// 写出只有一个压缩条目的 zip，并把中央目录中的解压后大小改为 `declared`
+    fn zip_with_declared_size(content: &[u8], declared: u32) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("webgal-archive-{}.zip", rand::random::<u32>()));
+        let mut writer = zip::ZipWriter::new(fs::File::create(&path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file("a.txt", options).unwrap();
+        writer.write_all(content).unwrap();
+        writer.finish().unwrap();
+
+        let mut data = fs::read(&path).unwrap();
+        let central = data
+            .windows(4)
+            .position(|window| window == b"PK\x01\x02")
+            .unwrap();
+        data[central + 24..central + 28].copy_from_slice(&declared.to_le_bytes());
+        fs::write(&path, data).unwrap();
+        path
+    }
+
+    #[test]
+    fn read_entry_matches_declared_size() {
+        let content = vec![b'a'; 4096];
+        let path = zip_with_declared_size(&content, content.len() as u32);
+        let index = ArchiveIndex::open(&path).unwrap();
+        let entry = index.entries().next().unwrap().1.clone();
+        assert_eq!(index.read_entry(&entry).unwrap(), content);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn read_entry_rejects_entry_larger_than_declared() {
+        let path = zip_with_declared_size(&[b'a'; 4096], 16);
+        let index = ArchiveIndex::open(&path).unwrap();
+        let entry = index.entries().next().unwrap().1.clone();
+        let error = index.read_entry(&entry).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,995 @@
+use super::access_log::AccessLogEntry;
+use super::archive::{self, ArchiveEntry, ArchiveIndex};
+use super::auth;
+use super::compression::{self, Encoding};
+use super::cors::{self, ALLOWED_METHODS};
+use super::events;
+use super::mime::{detect_mime_type, SNIFF_LEN};
+use super::thumbnail;
+use super::FileServerConfig;
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use urlencoding;
+
+/// multipart/byteranges 响应使用的分隔符
+const MULTIPART_BOUNDARY: &str = "WEBGAL_BYTERANGES_BOUNDARY";
+
+/// 流式发送文件时每次从磁盘读取的块大小
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 合并后最多接受的区间数，超过时忽略 Range 返回完整文件
+const MAX_RANGES: usize = 16;
+
+/// 与传输方式无关的资源请求
+pub struct AssetRequest {
+    /// 大写的请求方法，如 `GET`
+    pub method: String,
+    /// 请求路径，可以带查询参数，如 `/game/figure/a.png?v=1`
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl AssetRequest {
+    /// 读取请求头的值（大小写不敏感）
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(field, _)| field.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// 响应体
+pub enum AssetBody {
+    Empty,
+    /// HEAD 响应：只声明完整响应体的长度，不发送也不读取内容
+    Head(u64),
+    Bytes(Vec<u8>),
+    /// 缓存中共享的数据，如压缩后的文件
+    Shared(Arc<[u8]>),
+    /// 从磁盘按块读取的流，`len` 为总长度
+    Stream {
+        reader: Box<dyn Read + Send>,
+        len: u64,
+    },
+    /// 长度未知的长连接流，如事件流；每次读取到的数据都应立即发送
+    Live(Box<dyn Read + Send>),
+}
+
+impl AssetBody {
+    pub fn len(&self) -> u64 {
+        match self {
+            AssetBody::Empty => 0,
+            AssetBody::Head(len) => *len,
+            AssetBody::Bytes(bytes) => bytes.len() as u64,
+            AssetBody::Shared(bytes) => bytes.len() as u64,
+            AssetBody::Stream { len, .. } => *len,
+            AssetBody::Live(_) => 0,
+        }
+    }
+
+    /// 把响应体完整读入内存，供无法流式发送的传输方式使用
+    pub fn into_bytes(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            AssetBody::Empty | AssetBody::Head(_) => Ok(Vec::new()),
+            AssetBody::Bytes(bytes) => Ok(bytes),
+            AssetBody::Shared(bytes) => Ok(bytes.to_vec()),
+            AssetBody::Stream { mut reader, len } => {
+                let mut buffer = Vec::with_capacity(len.min(archive::MAX_PREALLOCATION) as usize);
+                reader.read_to_end(&mut buffer)?;
+                Ok(buffer)
+            }
+            AssetBody::Live(_) => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "长连接流无法一次性读取",
+            )),
+        }
+    }
+}
+
+/// 请求解析得到的资源：磁盘上的文件或 zip 包中的条目
+pub enum AssetSource {
+    File(PathBuf),
+    Archive {
+        index: Arc<ArchiveIndex>,
+        /// 包内路径
+        name: String,
+        entry: ArchiveEntry,
+    },
+}
+
+impl AssetSource {
+    /// 资源的路径，用于判断扩展名和作为缓存键；zip 条目为 `<zip 路径>/<包内路径>`
+    pub fn path(&self) -> PathBuf {
+        match self {
+            AssetSource::File(path) => path.clone(),
+            AssetSource::Archive { index, name, .. } => index.path().join(name),
+        }
+    }
+
+    /// 资源大小和修改时间；zip 条目使用 zip 文件本身的修改时间
+    pub fn stat(&self) -> std::io::Result<(u64, Option<SystemTime>)> {
+        match self {
+            AssetSource::File(path) => {
+                let metadata = fs::metadata(path)?;
+                Ok((metadata.len(), metadata.modified().ok()))
+            }
+            AssetSource::Archive { index, entry, .. } => Ok((entry.size, index.modified())),
+        }
+    }
+
+    /// 定位到 `start`，返回最多读取 `len` 字节的流
+    ///
+    /// 读取通过固定大小的缓冲区分块进行，不会把整个文件载入内存
+    pub fn open_range(&self, start: u64, len: u64) -> std::io::Result<Box<dyn Read + Send>> {
+        match self {
+            AssetSource::File(path) => {
+                let mut file = fs::File::open(path)?;
+                file.seek(SeekFrom::Start(start))?;
+                Ok(Box::new(
+                    BufReader::with_capacity(STREAM_CHUNK_SIZE, file).take(len),
+                ))
+            }
+            AssetSource::Archive { index, entry, .. } => index.open_entry(entry, start, len),
+        }
+    }
+
+    /// 把整个资源读入内存
+    pub fn read_all(&self) -> std::io::Result<Vec<u8>> {
+        match self {
+            AssetSource::File(path) => fs::read(path),
+            AssetSource::Archive { index, entry, .. } => index.read_entry(entry),
+        }
+    }
+
+    /// 读取用于内容嗅探的文件头
+    fn read_head(&self) -> Option<Vec<u8>> {
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        self.open_range(0, SNIFF_LEN as u64)
+            .ok()?
+            .read_to_end(&mut head)
+            .ok()?;
+        Some(head)
+    }
+}
+
+/// 与传输方式无关的资源响应
+pub struct AssetResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: AssetBody,
+}
+
+impl AssetResponse {
+    pub fn empty(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: AssetBody::Empty,
+        }
+    }
+
+    pub fn text(status: u16, text: &str) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: AssetBody::Bytes(text.as_bytes().to_vec()),
+        }
+        .with_header("Content-Type", "text/plain; charset=utf-8")
+    }
+
+    pub fn stream(status: u16, reader: Box<dyn Read + Send>, len: u64) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: AssetBody::Stream { reader, len },
+        }
+    }
+
+    pub fn with_header(mut self, field: &str, value: &str) -> Self {
+        self.headers.push((field.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// 处理一次资源请求，HTTP 服务器和 `webgal-asset://` 协议共用这一套逻辑
+///
+/// 每个请求都会写入访问日志，记录中的 URL 已去掉访问令牌
+pub fn serve_asset(request: &AssetRequest, config: &FileServerConfig) -> AssetResponse {
+    let started = Instant::now();
+    let (url, token) = auth::strip_token(request);
+    let response = respond(request, &url, token.as_deref(), config);
+
+    let bytes = if request.method.eq_ignore_ascii_case("HEAD") {
+        0
+    } else {
+        response.body.len()
+    };
+    config.access_log.record(AccessLogEntry::new(
+        &request.method,
+        &url,
+        response.status,
+        bytes,
+        started.elapsed(),
+        request.header("Range"),
+    ));
+    response
+}
+
+fn respond(
+    request: &AssetRequest,
+    url: &str,
+    token: Option<&str>,
+    config: &FileServerConfig,
+) -> AssetResponse {
+    let origin = request.header("Origin");
+
+    // 预检请求
+    if request.method.eq_ignore_ascii_case("OPTIONS") {
+        return cors::preflight_response(
+            origin,
+            request.header("Access-Control-Request-Method"),
+            &config.allowed_origins,
+        );
+    }
+
+    // 带 Origin 的跨域请求必须来自允许列表
+    if let Some(origin) = origin {
+        if !cors::is_origin_allowed(&config.allowed_origins, origin) {
+            log::warn!("拒绝来自未授权 Origin 的请求: {}", origin);
+            return AssetResponse::text(403, "Forbidden").with_header("Vary", "Origin");
+        }
+    }
+
+    // 校验访问令牌，之后只使用去掉令牌的 URL
+    if let Some(expected) = &config.access_token {
+        if !auth::token_matches(expected, token) {
+            let response = AssetResponse::text(401, "Unauthorized");
+            return cors::add_cors_headers(response, origin, &config.allowed_origins);
+        }
+    }
+
+    let is_head = request.method.eq_ignore_ascii_case("HEAD");
+    if !is_head && !request.method.eq_ignore_ascii_case("GET") {
+        let response =
+            AssetResponse::text(405, "Method Not Allowed").with_header("Allow", ALLOWED_METHODS);
+        return cors::add_cors_headers(response, origin, &config.allowed_origins);
+    }
+
+    let route = url.split('?').next().unwrap_or(url);
+    let mut response = if route == events::EVENTS_PATH {
+        events::serve_events(config)
+    } else {
+        match url.strip_prefix(thumbnail::THUMBNAIL_PREFIX) {
+            Some(path) => thumbnail::serve_thumbnail(request, path, config),
+            None => serve_file(request, url, config),
+        }
+    };
+    if is_head {
+        // HEAD 保留 Content-Length 等响应头，但不发送响应体
+        response.body = AssetBody::Head(response.body.len());
+    }
+    cors::add_cors_headers(response, origin, &config.allowed_origins)
+}
+
+/// 把请求 URL 解析为要读取的资源，失败时返回对应的错误响应
+///
+/// `url` 可以带查询参数；路径会先做 URL 解码，再按挂载点和根目录解析。
+/// 根目录或挂载点是 zip 文件（或 zip 内的目录）时从包内读取
+pub(super) fn resolve_request_source(
+    url: &str,
+    config: &FileServerConfig,
+) -> Result<AssetSource, AssetResponse> {
+    // 移除查询参数
+    let path_part_encoded = url.split('?').next().unwrap_or(url);
+
+    // 解码 URL 编码的路径
+    let path_part = urlencoding::decode(path_part_encoded)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|_| path_part_encoded.to_string());
+
+    // 选择挂载点
+    let (base_path, relative_path) = resolve_mount(config, &path_part);
+
+    if let Some((archive_path, prefix)) = archive::split_archive_path(Path::new(&base_path)) {
+        return resolve_archive_entry(config, &archive_path, &prefix, relative_path);
+    }
+
+    // 构造完整文件路径，拒绝任何逃逸出根目录的请求
+    let file_path = match resolve_safe_path(Path::new(&base_path), relative_path) {
+        Ok(file_path) => file_path,
+        Err(ResolveError::Forbidden) => {
+            log::warn!("拒绝访问根目录之外的路径: {}", path_part);
+            return Err(AssetResponse::text(403, "Forbidden"));
+        }
+        Err(ResolveError::NotFound) => {
+            return Err(AssetResponse::text(404, "File not found"));
+        }
+    };
+
+    log::trace!("{} -> {:?}", path_part, file_path);
+
+    // 检查是否是文件
+    if !file_path.is_file() {
+        return Err(AssetResponse::text(404, "File not found"));
+    }
+    Ok(AssetSource::File(file_path))
+}
+
+/// 在 zip 中查找请求的条目，`prefix` 是根目录在包内的路径
+fn resolve_archive_entry(
+    config: &FileServerConfig,
+    archive_path: &Path,
+    prefix: &str,
+    request_path: &str,
+) -> Result<AssetSource, AssetResponse> {
+    let relative = match normalize_request_path(request_path) {
+        Ok(relative) => relative,
+        Err(_) => {
+            log::warn!("拒绝访问根目录之外的路径: {}", request_path);
+            return Err(AssetResponse::text(403, "Forbidden"));
+        }
+    };
+
+    let index = config.archives.get(archive_path).map_err(|e| {
+        log::error!("读取 zip 失败 {:?}: {}", archive_path, e);
+        AssetResponse::text(500, &format!("Internal Server Error: {}", e))
+    })?;
+
+    let relative = relative.join("/");
+    let name = match (prefix.is_empty(), relative.is_empty()) {
+        (true, _) => relative,
+        (false, true) => prefix.to_string(),
+        (false, false) => format!("{}/{}", prefix, relative),
+    };
+    log::trace!("{} -> {:?}!{}", request_path, archive_path, name);
+
+    match index.entry(&name).cloned() {
+        Some(entry) => Ok(AssetSource::Archive { index, name, entry }),
+        None => Err(AssetResponse::text(404, "File not found")),
+    }
+}
+
+/// 处理 GET 请求：解析路径并返回文件内容，`url` 已去掉访问令牌
+fn serve_file(request: &AssetRequest, url: &str, config: &FileServerConfig) -> AssetResponse {
+    let source = match resolve_request_source(url, config) {
+        Ok(source) => source,
+        Err(response) => return response,
+    };
+
+    // 读取文件元数据
+    let (file_len, modified) = match source.stat() {
+        Ok(stat) => stat,
+        Err(e) => {
+            log::error!("读取文件信息失败: {}", e);
+            return AssetResponse::text(500, &format!("Internal Server Error: {}", e));
+        }
+    };
+    let last_modified = modified.map(httpdate::fmt_http_date);
+
+    // 判断 MIME 类型
+    let mime_type = detect_mime_type(&source.path(), || source.read_head());
+
+    // 协商压缩方式；Range 请求总是针对原始字节，不压缩
+    let compressible = compression::is_compressible(&mime_type);
+    let encoding = if request.header("Range").is_none() {
+        compression::negotiate(request.header("Accept-Encoding"), &mime_type, file_len)
+    } else {
+        None
+    };
+    let identity_etag = compute_etag(file_len, modified);
+    let etag = match encoding {
+        Some(encoding) => compression::variant_etag(&identity_etag, encoding),
+        None => identity_etag.clone(),
+    };
+
+    // 条件请求：资源未变化时返回 304
+    if is_not_modified(request, &etag, modified) {
+        let mut response = AssetResponse::empty(304);
+        if compressible {
+            response = response.with_header("Vary", "Accept-Encoding");
+        }
+        return with_cache_headers(
+            response,
+            &etag,
+            last_modified.as_deref(),
+            &config.cache_control,
+        );
+    }
+
+    // 解析 Range 请求头；If-Range 不匹配时忽略 Range，返回完整文件
+    let range_header = request
+        .header("Range")
+        .filter(|_| if_range_matches(request, &identity_etag, modified));
+    let ranges = range_header.and_then(|value| parse_range_header(value, file_len));
+
+    let result = match ranges {
+        // 没有 Range 头或格式无效：返回完整文件，协商成功时返回压缩版本
+        None => match encoding {
+            Some(encoding) => {
+                send_compressed(config, &source, modified, file_len, encoding, &mime_type)
+            }
+            None => source.open_range(0, file_len).map(|reader| {
+                AssetResponse::stream(200, reader, file_len).with_header("Content-Type", &mime_type)
+            }),
+        },
+        // 所有区间都无法满足
+        Some(ranges) if ranges.is_empty() => {
+            let response = AssetResponse::text(416, "Range Not Satisfiable")
+                .with_header("Content-Range", &format!("bytes */{}", file_len));
+            return response;
+        }
+        // 单个区间：206 + Content-Range
+        Some(ranges) if ranges.len() == 1 => {
+            let (start, end) = ranges[0];
+            source.open_range(start, end - start + 1).map(|reader| {
+                AssetResponse::stream(206, reader, end - start + 1)
+                    .with_header(
+                        "Content-Range",
+                        &format!("bytes {}-{}/{}", start, end, file_len),
+                    )
+                    .with_header("Content-Type", &mime_type)
+            })
+        }
+        // 多个区间：206 + multipart/byteranges
+        Some(ranges) => {
+            let (reader, len) = MultipartReader::new(source, &ranges, file_len, &mime_type);
+            Ok(
+                AssetResponse::stream(206, Box::new(reader), len).with_header(
+                    "Content-Type",
+                    &format!("multipart/byteranges; boundary={}", MULTIPART_BOUNDARY),
+                ),
+            )
+        }
+    };
+
+    match result {
+        Ok(mut response) => {
+            response = response.with_header("Accept-Ranges", "bytes");
+            if compressible {
+                response = response.with_header("Vary", "Accept-Encoding");
+            }
+            with_cache_headers(
+                response,
+                &etag,
+                last_modified.as_deref(),
+                &config.cache_control,
+            )
+        }
+        Err(e) => {
+            log::error!("读取文件失败: {}", e);
+            AssetResponse::text(500, &format!("Internal Server Error: {}", e))
+        }
+    }
+}
+
+/// 从缓存中取出（或生成）压缩后的文件
+fn send_compressed(
+    config: &FileServerConfig,
+    source: &AssetSource,
+    modified: Option<SystemTime>,
+    file_len: u64,
+    encoding: Encoding,
+    mime_type: &str,
+) -> std::io::Result<AssetResponse> {
+    let data = config.compression_cache.get_or_compress(
+        &source.path(),
+        modified,
+        file_len,
+        encoding,
+        || source.read_all(),
+    )?;
+    Ok(AssetResponse {
+        status: 200,
+        headers: Vec::new(),
+        body: AssetBody::Shared(data),
+    }
+    .with_header("Content-Type", mime_type)
+    .with_header("Content-Encoding", encoding.as_str()))
+}
+
+/// 路径解析失败的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveError {
+    /// 路径试图访问根目录之外的文件（`..`、绝对路径或符号链接逃逸）
+    Forbidden,
+    /// 路径合法但文件不存在
+    NotFound,
+}
+
+/// 将解码后的请求路径解析为根目录下的真实路径
+///
+/// 只接受普通路径段；解析后的路径经过 canonicalize，
+/// 因此指向根目录之外的符号链接同样会被拒绝
+pub fn resolve_safe_path(base: &Path, request_path: &str) -> Result<PathBuf, ResolveError> {
+    let relative: PathBuf = normalize_request_path(request_path)?.into_iter().collect();
+
+    let canonical_base = base.canonicalize().map_err(|_| ResolveError::NotFound)?;
+    let canonical_path = canonical_base
+        .join(&relative)
+        .canonicalize()
+        .map_err(|_| ResolveError::NotFound)?;
+
+    if canonical_path.starts_with(&canonical_base) {
+        Ok(canonical_path)
+    } else {
+        Err(ResolveError::Forbidden)
+    }
+}
+
+/// 把请求路径拆分为普通路径段，遇到 `..`、绝对路径等特殊段时拒绝
+fn normalize_request_path(request_path: &str) -> Result<Vec<&str>, ResolveError> {
+    if request_path.contains('\0') {
+        return Err(ResolveError::Forbidden);
+    }
+
+    // 同时按 `/` 和 `\` 切分，避免 Windows 上的反斜杠绕过检查
+    let mut segments = Vec::new();
+    for segment in request_path.split(['/', '\\']) {
+        if segment.is_empty() || segment == "." {
+            continue;
+        }
+        let mut components = Path::new(segment).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) => segments.push(segment),
+            _ => return Err(ResolveError::Forbidden),
+        }
+    }
+    Ok(segments)
+}
+
+/// 根据请求路径的第一段选择挂载点，返回挂载目录和剩余的相对路径
+pub fn resolve_mount<'a>(config: &FileServerConfig, request_path: &'a str) -> (String, &'a str) {
+    let trimmed = request_path.trim_start_matches('/');
+    let (first, rest) = trimmed.split_once('/').unwrap_or((trimmed, ""));
+
+    if let Ok(mounts) = config.mounts.read() {
+        if let Some(mount_path) = mounts.get(first) {
+            return (mount_path.clone(), rest);
+        }
+    }
+
+    (config.base_path.clone(), trimmed)
+}
+
+/// 根据文件大小和修改时间计算强 ETag
+pub(super) fn compute_etag(file_len: u64, modified: Option<SystemTime>) -> String {
+    let mtime = modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", mtime, file_len)
+}
+
+/// 判断 `If-None-Match` 中是否包含给定的 ETag（弱比较）
+fn etag_matches(header: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// HTTP 日期只精确到秒，比较前需要截断
+fn truncate_to_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 判断条件请求是否可以返回 304
+///
+/// 按 RFC 7232，存在 `If-None-Match` 时忽略 `If-Modified-Since`
+pub(super) fn is_not_modified(
+    request: &AssetRequest,
+    etag: &str,
+    modified: Option<SystemTime>,
+) -> bool {
+    if let Some(if_none_match) = request.header("If-None-Match") {
+        return etag_matches(if_none_match, etag);
+    }
+
+    if let (Some(if_modified_since), Some(modified)) =
+        (request.header("If-Modified-Since"), modified)
+    {
+        if let Ok(since) = httpdate::parse_http_date(if_modified_since) {
+            return truncate_to_secs(modified) <= truncate_to_secs(since);
+        }
+    }
+
+    false
+}
+
+/// 判断 `If-Range` 是否仍然匹配当前文件；没有 `If-Range` 时总是匹配
+fn if_range_matches(request: &AssetRequest, etag: &str, modified: Option<SystemTime>) -> bool {
+    let Some(if_range) = request.header("If-Range") else {
+        return true;
+    };
+
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        // If-Range 要求强比较，弱 ETag 永远不匹配
+        return if_range == etag;
+    }
+
+    match (httpdate::parse_http_date(if_range), modified) {
+        (Ok(date), Some(modified)) => truncate_to_secs(modified) == truncate_to_secs(date),
+        _ => false,
+    }
+}
+
+/// 添加 ETag / Last-Modified / Cache-Control 响应头
+pub(super) fn with_cache_headers(
+    response: AssetResponse,
+    etag: &str,
+    last_modified: Option<&str>,
+    cache_control: &str,
+) -> AssetResponse {
+    let mut response = response.with_header("ETag", etag);
+    if let Some(last_modified) = last_modified {
+        response = response.with_header("Last-Modified", last_modified);
+    }
+    if !cache_control.is_empty() {
+        response = response.with_header("Cache-Control", cache_control);
+    }
+    response
+}
+
+/// 解析 `Range: bytes=...` 请求头，返回闭区间 `(start, end)` 列表
+///
+/// 重叠或相邻的区间会按起点排序后合并
+///
+/// - 返回 `None` 表示请求头无法识别，或合并后区间超过 [`MAX_RANGES`] 个，
+///   按 RFC 7233 应忽略并返回完整文件
+/// - 返回空列表表示所有区间都无法满足，应返回 416
+fn parse_range_header(value: &str, file_len: u64) -> Option<Vec<(u64, u64)>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    let mut ranges = Vec::new();
+
+    for part in spec.split(',') {
+        let part = part.trim();
+        if part.is_empty() {
+            continue;
+        }
+        let (start_str, end_str) = part.split_once('-')?;
+        let (start_str, end_str) = (start_str.trim(), end_str.trim());
+
+        if start_str.is_empty() {
+            // 后缀区间：bytes=-500 表示最后 500 字节
+            let suffix_len: u64 = end_str.parse().ok()?;
+            if suffix_len == 0 || file_len == 0 {
+                continue;
+            }
+            let start = file_len.saturating_sub(suffix_len);
+            ranges.push((start, file_len - 1));
+        } else {
+            let start: u64 = start_str.parse().ok()?;
+            let end: u64 = if end_str.is_empty() {
+                u64::MAX
+            } else {
+                end_str.parse().ok()?
+            };
+            if end < start {
+                return None;
+            }
+            // 起点超出文件长度的区间无法满足，直接跳过
+            if start >= file_len {
+                continue;
+            }
+            ranges.push((start, end.min(file_len - 1)));
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len().min(MAX_RANGES + 1));
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => {
+                if merged.len() == MAX_RANGES {
+                    return None;
+                }
+                merged.push((start, end));
+            }
+        }
+    }
+    Some(merged)
+}
+
+/// multipart/byteranges 响应体中的一段
+enum MultipartPart {
+    /// 分隔符和区间头
+    Bytes(Vec<u8>),
+    /// 文件中的一个区间，读到时才打开
+    Range { start: u64, len: u64 },
+}
+
+/// 依次发送 multipart/byteranges 响应体的各段，同一时间只打开一个文件句柄
+struct MultipartReader {
+    source: AssetSource,
+    parts: VecDeque<MultipartPart>,
+    current: Option<Box<dyn Read + Send>>,
+}
+
+impl MultipartReader {
+    /// 构造响应体，同时返回其总长度
+    fn new(
+        source: AssetSource,
+        ranges: &[(u64, u64)],
+        file_len: u64,
+        mime_type: &str,
+    ) -> (Self, u64) {
+        let mut parts = VecDeque::with_capacity(ranges.len() * 2 + 1);
+        let mut total_len = 0u64;
+
+        for (i, &(start, end)) in ranges.iter().enumerate() {
+            // 每段之前的 CRLF 结束上一段的数据
+            let part_header = format!(
+                "{}--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                if i == 0 { "" } else { "\r\n" },
+                MULTIPART_BOUNDARY,
+                mime_type,
+                start,
+                end,
+                file_len
+            );
+            let len = end - start + 1;
+            total_len += part_header.len() as u64 + len;
+            parts.push_back(MultipartPart::Bytes(part_header.into_bytes()));
+            parts.push_back(MultipartPart::Range { start, len });
+        }
+
+        let closing = format!("\r\n--{}--\r\n", MULTIPART_BOUNDARY);
+        total_len += closing.len() as u64;
+        parts.push_back(MultipartPart::Bytes(closing.into_bytes()));
+
+        let reader = Self {
+            source,
+            parts,
+            current: None,
+        };
+        (reader, total_len)
+    }
+}
+
+impl Read for MultipartReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if let Some(current) = self.current.as_mut() {
+                let read = current.read(buf)?;
+                if read > 0 || buf.is_empty() {
+                    return Ok(read);
+                }
+                self.current = None;
+            }
+
+            self.current = match self.parts.pop_front() {
+                Some(MultipartPart::Bytes(bytes)) => Some(Box::new(Cursor::new(bytes))),
+                Some(MultipartPart::Range { start, len }) => {
+                    Some(self.source.open_range(start, len)?)
+                }
+                None => return Ok(0),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_suffix_and_open_ended() {
+        assert_eq!(
+            parse_range_header("bytes=-500", 1000),
+            Some(vec![(500, 999)])
+        );
+        assert_eq!(
+            parse_range_header("bytes=-5000", 1000),
+            Some(vec![(0, 999)])
+        );
+        assert_eq!(
+            parse_range_header("bytes=100-", 1000),
+            Some(vec![(100, 999)])
+        );
+        assert_eq!(parse_range_header("bytes=0-99", 1000), Some(vec![(0, 99)]));
+        assert_eq!(
+            parse_range_header("bytes=900-5000", 1000),
+            Some(vec![(900, 999)])
+        );
+    }
+
+    #[test]
+    fn range_unsatisfiable() {
+        assert_eq!(parse_range_header("bytes=1000-", 1000), Some(vec![]));
+        assert_eq!(parse_range_header("bytes=-0", 1000), Some(vec![]));
+        assert_eq!(parse_range_header("bytes=-10", 0), Some(vec![]));
+    }
+
+    #[test]
+    fn range_invalid_is_ignored() {
+        assert_eq!(parse_range_header("items=0-1", 1000), None);
+        assert_eq!(parse_range_header("bytes=5-1", 1000), None);
+        assert_eq!(parse_range_header("bytes=a-b", 1000), None);
+        assert_eq!(parse_range_header("bytes=10", 1000), None);
+    }
+
+    #[test]
+    fn range_multi_is_sorted_and_merged() {
+        assert_eq!(
+            parse_range_header("bytes=500-599,0-99", 1000),
+            Some(vec![(0, 99), (500, 599)])
+        );
+        assert_eq!(
+            parse_range_header("bytes=0-,0-,0-,0-", 1000),
+            Some(vec![(0, 999)])
+        );
+        // 重叠和相邻的区间都会合并
+        assert_eq!(
+            parse_range_header("bytes=0-99,50-149,150-199,300-399", 1000),
+            Some(vec![(0, 199), (300, 399)])
+        );
+    }
+
+    #[test]
+    fn range_count_is_capped() {
+        let within: Vec<String> = (0..MAX_RANGES)
+            .map(|i| format!("{}-{}", i * 10, i * 10))
+            .collect();
+        let header = format!("bytes={}", within.join(","));
+        assert_eq!(
+            parse_range_header(&header, 1000).map(|r| r.len()),
+            Some(MAX_RANGES)
+        );
+
+        let too_many: Vec<String> = (0..20_000)
+            .map(|i| format!("{}-{}", i * 2, i * 2))
+            .collect();
+        let header = format!("bytes={}", too_many.join(","));
+        assert_eq!(parse_range_header(&header, 100_000), None);
+
+        // 大量相邻区间合并后只剩一个
+        let adjacent: Vec<String> = (0..20_000).map(|i| format!("{}-{}", i, i)).collect();
+        let header = format!("bytes={}", adjacent.join(","));
+        assert_eq!(
+            parse_range_header(&header, 100_000),
+            Some(vec![(0, 19_999)])
+        );
+    }
+
+    #[test]
+    fn multipart_body_matches_declared_length() {
+        let path = std::env::temp_dir().join(format!("webgal-multipart-{}", rand::random::<u32>()));
+        fs::write(&path, b"0123456789").unwrap();
+
+        let (mut reader, len) = MultipartReader::new(
+            AssetSource::File(path.clone()),
+            &[(0, 1), (8, 9)],
+            10,
+            "text/plain",
+        );
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(body.len() as u64, len);
+        let expected = format!(
+            "--{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+             --{b}\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n\
+             --{b}--\r\n",
+            b = MULTIPART_BOUNDARY
+        );
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+    }
+
+    #[test]
+    fn normalize_rejects_traversal() {
+        assert_eq!(normalize_request_path("a/./b//c"), Ok(vec!["a", "b", "c"]));
+        assert_eq!(
+            normalize_request_path("../secret"),
+            Err(ResolveError::Forbidden)
+        );
+        assert_eq!(
+            normalize_request_path("a/../../b"),
+            Err(ResolveError::Forbidden)
+        );
+        assert_eq!(
+            normalize_request_path("a\\..\\..\\b"),
+            Err(ResolveError::Forbidden)
+        );
+        assert_eq!(normalize_request_path("a\0b"), Err(ResolveError::Forbidden));
+        // 开头的 `/` 只是空路径段，绝对路径被当作根目录下的相对路径
+        assert_eq!(
+            normalize_request_path("/etc/passwd"),
+            Ok(vec!["etc", "passwd"])
+        );
+        assert_eq!(
+            normalize_request_path("\\\\server\\share"),
+            Ok(vec!["server", "share"])
+        );
+    }
+
+    /// 在系统临时目录下创建 `root/inside.txt` 和与 `root` 同级的 `outside.txt`
+    fn temp_root(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("webgal-{}-{}", name, rand::random::<u32>()));
+        fs::create_dir_all(dir.join("root/sub")).unwrap();
+        fs::write(dir.join("root/inside.txt"), b"inside").unwrap();
+        fs::write(dir.join("outside.txt"), b"outside").unwrap();
+        dir
+    }
+
+    #[test]
+    fn resolve_stays_inside_base() {
+        let dir = temp_root("resolve");
+        let base = dir.join("root");
+
+        assert_eq!(
+            resolve_safe_path(&base, "sub/../inside.txt"),
+            Err(ResolveError::Forbidden)
+        );
+        assert_eq!(
+            resolve_safe_path(&base, "inside.txt"),
+            Ok(base.join("inside.txt").canonicalize().unwrap())
+        );
+        assert_eq!(
+            resolve_safe_path(&base, "..\\outside.txt"),
+            Err(ResolveError::Forbidden)
+        );
+        assert_eq!(
+            resolve_safe_path(&base, "inside.txt\0"),
+            Err(ResolveError::Forbidden)
+        );
+        let absolute = dir.join("outside.txt").to_string_lossy().to_string();
+        assert_eq!(
+            resolve_safe_path(&base, &absolute),
+            Err(ResolveError::NotFound)
+        );
+        assert_eq!(
+            resolve_safe_path(&base, "missing.txt"),
+            Err(ResolveError::NotFound)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_rejects_symlink_escape() {
+        let dir = temp_root("symlink");
+        let base = dir.join("root");
+        std::os::unix::fs::symlink(dir.join("outside.txt"), base.join("link.txt")).unwrap();
+        std::os::unix::fs::symlink(&dir, base.join("up")).unwrap();
+
+        assert_eq!(
+            resolve_safe_path(&base, "link.txt"),
+            Err(ResolveError::Forbidden)
+        );
+        assert_eq!(
+            resolve_safe_path(&base, "up/outside.txt"),
+            Err(ResolveError::Forbidden)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn head_advertises_length_without_reading() {
+        let dir = temp_root("head");
+        let config = FileServerConfig::new(dir.join("root").to_string_lossy().to_string());
+        let request = AssetRequest {
+            method: "HEAD".to_string(),
+            url: "/inside.txt".to_string(),
+            headers: Vec::new(),
+        };
+
+        let response = serve_asset(&request, &config);
+        assert_eq!(response.status, 200);
+        assert!(matches!(response.body, AssetBody::Head(6)));
+        assert_eq!(response.body.into_bytes().unwrap(), Vec::<u8>::new());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,78 @@
+use super::asset::AssetRequest;
+use rand::RngCore;
+
+/// 携带访问令牌的请求头
+pub const TOKEN_HEADER: &str = "X-WebGAL-Token";
+
+/// 携带访问令牌的查询参数
+pub const TOKEN_QUERY_PARAM: &str = "token";
+
+/// 路径形式的令牌前缀：`/__session/<token>/...`
+///
+/// Live2D 等模型按相对路径加载贴图和动作时会丢失查询参数，
+/// 把令牌放在路径里可以让这些相对请求自动带上令牌
+pub const TOKEN_PATH_PREFIX: &str = "/__session/";
+
+/// 生成 256 位随机令牌（十六进制）
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 从请求中取出令牌并返回去掉令牌后的 URL
+///
+/// 返回的 URL 不含任何令牌信息，可以安全地写入日志
+pub fn strip_token(request: &AssetRequest) -> (String, Option<String>) {
+    let url = request.url.as_str();
+    let (path, query) = match url.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (url, None),
+    };
+
+    let mut token = request.header(TOKEN_HEADER).map(str::to_string);
+
+    // 路径前缀形式
+    let path = match path.strip_prefix(TOKEN_PATH_PREFIX) {
+        Some(rest) => {
+            let (path_token, rest) = rest.split_once('/').unwrap_or((rest, ""));
+            token.get_or_insert_with(|| path_token.to_string());
+            format!("/{}", rest)
+        }
+        None => path.to_string(),
+    };
+
+    // 查询参数形式，其余参数原样保留
+    let mut kept = Vec::new();
+    for pair in query.unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+        match pair.split_once('=') {
+            Some((key, value)) if key == TOKEN_QUERY_PARAM => {
+                token.get_or_insert_with(|| value.to_string());
+            }
+            _ => kept.push(pair),
+        }
+    }
+
+    let cleaned = if kept.is_empty() {
+        path
+    } else {
+        format!("{}?{}", path, kept.join("&"))
+    };
+    (cleaned, token)
+}
+
+/// 常数时间比较，避免通过响应时间猜测令牌
+pub fn token_matches(expected: &str, provided: Option<&str>) -> bool {
+    let Some(provided) = provided else {
+        return false;
+    };
+    let (expected, provided) = (expected.as_bytes(), provided.as_bytes());
+    if expected.len() != provided.len() {
+        return false;
+    }
+    expected
+        .iter()
+        .zip(provided)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
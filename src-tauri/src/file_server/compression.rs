@@ -0,0 +1,208 @@
+use flate2::write::GzEncoder;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// 小于该大小的文件压缩收益不大，直接原样发送
+const MIN_COMPRESS_LEN: u64 = 1024;
+
+/// 超过该大小的文本文件不压缩，避免一次性读入过多内存
+const MAX_COMPRESS_LEN: u64 = 32 * 1024 * 1024;
+
+/// 压缩结果缓存的总容量上限
+const CACHE_CAPACITY_BYTES: usize = 64 * 1024 * 1024;
+
+/// Brotli 压缩等级，在速度和压缩率之间取中间值
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+
+/// 支持的内容编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    /// `Content-Encoding` 响应头的值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+}
+
+/// 是否是值得压缩的文本类 MIME 类型
+pub fn is_compressible(mime_type: &str) -> bool {
+    let essence = mime_type.split(';').next().unwrap_or("").trim();
+    essence.starts_with("text/")
+        || matches!(
+            essence,
+            "application/json"
+                | "application/x-ndjson"
+                | "application/xml"
+                | "application/javascript"
+                | "image/svg+xml"
+        )
+}
+
+/// 根据 `Accept-Encoding`、MIME 类型和文件大小选择压缩方式
+///
+/// 优先 Brotli，其次 gzip；q=0 表示客户端明确拒绝该编码
+pub fn negotiate(
+    accept_encoding: Option<&str>,
+    mime_type: &str,
+    file_len: u64,
+) -> Option<Encoding> {
+    if !is_compressible(mime_type) || !(MIN_COMPRESS_LEN..=MAX_COMPRESS_LEN).contains(&file_len) {
+        return None;
+    }
+
+    let mut brotli = None;
+    let mut gzip = None;
+    let mut wildcard = None;
+
+    for item in accept_encoding?.split(',') {
+        let mut parts = item.split(';');
+        let coding = parts.next().unwrap_or("").trim().to_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+
+        match coding.as_str() {
+            "br" => brotli = Some(quality),
+            "gzip" | "x-gzip" => gzip = Some(quality),
+            "*" => wildcard = Some(quality),
+            _ => {}
+        }
+    }
+
+    let accepts = |quality: Option<f32>| quality.or(wildcard).is_some_and(|q| q > 0.0);
+    if accepts(brotli) {
+        Some(Encoding::Brotli)
+    } else if accepts(gzip) {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+/// 压缩后的表示使用不同的 ETag，避免与未压缩版本混淆
+pub fn variant_etag(etag: &str, encoding: Encoding) -> String {
+    format!("{}-{}\"", etag.trim_end_matches('"'), encoding.as_str())
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    modified: Option<SystemTime>,
+    len: u64,
+    data: Arc<[u8]>,
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    entries: HashMap<(PathBuf, Encoding), CacheEntry>,
+    total_bytes: usize,
+    tick: u64,
+}
+
+/// 压缩结果缓存，以文件路径和编码为键，文件的 mtime 或大小变化后自动失效
+#[derive(Debug, Default)]
+pub struct CompressionCache {
+    inner: Mutex<CacheInner>,
+}
+
+impl CompressionCache {
+    /// 取出缓存的压缩结果，不存在或已过期时通过 `read` 读取原始数据重新压缩
+    ///
+    /// `path` 只用作缓存键，zip 条目使用 `<zip 路径>/<包内路径>`
+    pub fn get_or_compress(
+        &self,
+        path: &Path,
+        modified: Option<SystemTime>,
+        len: u64,
+        encoding: Encoding,
+        read: impl FnOnce() -> std::io::Result<Vec<u8>>,
+    ) -> std::io::Result<Arc<[u8]>> {
+        let key = (path.to_path_buf(), encoding);
+
+        if let Ok(mut inner) = self.inner.lock() {
+            inner.tick += 1;
+            let tick = inner.tick;
+            if let Some(entry) = inner.entries.get_mut(&key) {
+                if entry.modified == modified && entry.len == len {
+                    entry.last_used = tick;
+                    return Ok(Arc::clone(&entry.data));
+                }
+            }
+        }
+
+        // 压缩在锁外进行，避免阻塞其他工作线程
+        let data: Arc<[u8]> = compress(&read()?, encoding)?.into();
+
+        if let Ok(mut inner) = self.inner.lock() {
+            if data.len() <= CACHE_CAPACITY_BYTES {
+                inner.tick += 1;
+                let entry = CacheEntry {
+                    modified,
+                    len,
+                    data: Arc::clone(&data),
+                    last_used: inner.tick,
+                };
+                inner.total_bytes += entry.data.len();
+                if let Some(previous) = inner.entries.insert(key, entry) {
+                    inner.total_bytes -= previous.data.len();
+                }
+                inner.evict();
+            }
+        }
+
+        Ok(data)
+    }
+}
+
+impl CacheInner {
+    /// 超出容量时按最近最少使用淘汰
+    fn evict(&mut self) {
+        while self.total_bytes > CACHE_CAPACITY_BYTES {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                self.total_bytes -= entry.data.len();
+            }
+        }
+    }
+}
+
+fn compress(data: &[u8], encoding: Encoding) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        Encoding::Brotli => {
+            let mut output = Vec::with_capacity(data.len() / 4);
+            {
+                let mut writer =
+                    brotli::CompressorWriter::new(&mut output, 4096, BROTLI_QUALITY, BROTLI_WINDOW);
+                writer.write_all(data)?;
+            }
+            Ok(output)
+        }
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(
+                Vec::with_capacity(data.len() / 4),
+                flate2::Compression::default(),
+            );
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::asset::AssetResponse;
+use std::sync::{Arc, RwLock};
+
+/// 允许读取资源的 Origin 列表，`*` 表示允许任意来源
+pub type OriginList = Arc<RwLock<Vec<String>>>;
+
+/// 编辑器自身 webview 的 Origin（生产环境的各平台形式）
+pub const DEFAULT_ALLOWED_ORIGINS: &[&str] = &[
+    "tauri://localhost",
+    "http://tauri.localhost",
+    "https://tauri.localhost",
+];
+
+/// `tauri dev` 的开发服务器，只在调试构建中允许
+const DEV_SERVER_ORIGIN: &str = "http://localhost:1421";
+
+/// 服务器支持的请求方法
+pub const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
+/// 允许跨域请求携带的请求头
+const ALLOWED_HEADERS: &str =
+    "Content-Type, Range, If-None-Match, If-Modified-Since, If-Range, Accept-Encoding, X-WebGAL-Token";
+
+/// 允许前端读取的响应头
+const EXPOSED_HEADERS: &str =
+    "Accept-Ranges, Content-Range, Content-Length, Content-Encoding, ETag, Last-Modified";
+
+/// 预检结果的缓存时间（秒）
+const PREFLIGHT_MAX_AGE: &str = "600";
+
+pub fn default_origins() -> OriginList {
+    let mut origins: Vec<String> = DEFAULT_ALLOWED_ORIGINS
+        .iter()
+        .map(|origin| origin.to_string())
+        .collect();
+    if cfg!(debug_assertions) {
+        origins.push(DEV_SERVER_ORIGIN.to_string());
+    }
+    Arc::new(RwLock::new(origins))
+}
+
+/// 判断 Origin 是否在允许列表中（忽略末尾斜杠和大小写）
+pub fn is_origin_allowed(allowed: &OriginList, origin: &str) -> bool {
+    let origin = origin.trim_end_matches('/');
+    allowed.read().is_ok_and(|allowed| {
+        allowed.iter().any(|candidate| {
+            candidate == "*" || candidate.trim_end_matches('/').eq_ignore_ascii_case(origin)
+        })
+    })
+}
+
+/// 为允许的 Origin 添加 CORS 响应头；不在列表中的 Origin 不会得到任何 CORS 头
+pub fn add_cors_headers(
+    response: AssetResponse,
+    origin: Option<&str>,
+    allowed: &OriginList,
+) -> AssetResponse {
+    let response = response.with_header("Vary", "Origin");
+    match origin {
+        Some(origin) if is_origin_allowed(allowed, origin) => response
+            .with_header("Access-Control-Allow-Origin", origin)
+            .with_header("Access-Control-Expose-Headers", EXPOSED_HEADERS),
+        _ => response,
+    }
+}
+
+/// 构造 CORS 预检响应
+pub fn preflight_response(
+    origin: Option<&str>,
+    request_method: Option<&str>,
+    allowed: &OriginList,
+) -> AssetResponse {
+    let method_allowed = request_method.is_some_and(|method| {
+        ALLOWED_METHODS
+            .split(", ")
+            .any(|allowed| allowed.eq_ignore_ascii_case(method.trim()))
+    });
+
+    match origin {
+        Some(origin) if method_allowed && is_origin_allowed(allowed, origin) => {
+            AssetResponse::empty(204)
+                .with_header("Access-Control-Allow-Origin", origin)
+                .with_header("Access-Control-Allow-Methods", ALLOWED_METHODS)
+                .with_header("Access-Control-Allow-Headers", ALLOWED_HEADERS)
+                .with_header("Access-Control-Max-Age", PREFLIGHT_MAX_AGE)
+                .with_header("Vary", "Origin")
+        }
+        // 普通的 OPTIONS 请求（非预检）只返回支持的方法
+        None => AssetResponse::empty(204).with_header("Allow", ALLOWED_METHODS),
+        _ => AssetResponse::text(403, "Forbidden").with_header("Vary", "Origin"),
+    }
+}
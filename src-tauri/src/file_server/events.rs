@@ -0,0 +1,95 @@
+use super::asset::{AssetBody, AssetResponse};
+use super::watch::AssetChanges;
+use super::FileServerConfig;
+use std::io::{self, Cursor, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 资源变化事件流（Server-Sent Events）的请求路径
+pub const EVENTS_PATH: &str = "/__events";
+
+/// 没有变化时发送注释行的间隔，用于保持连接并及时发现已断开的客户端
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// 断线后 EventSource 重新连接前等待的毫秒数
+const RETRY_MS: u32 = 3000;
+
+/// 同时打开的事件流上限，每个连接在 HTTP 服务器中独占一个线程，超出时返回 503
+pub const MAX_EVENT_STREAMS: usize = 16;
+
+/// 打开事件流，每批变化作为一条 `change` 事件发送，数据为 JSON
+///
+/// 响应体是长度未知的长连接流，只能通过 HTTP 服务器发送
+pub fn serve_events(config: &FileServerConfig) -> AssetResponse {
+    // 先占用名额，超出上限时立即归还
+    if config.event_streams.fetch_add(1, Ordering::Relaxed) >= MAX_EVENT_STREAMS {
+        config.event_streams.fetch_sub(1, Ordering::Relaxed);
+        return AssetResponse::text(503, "Too many event streams");
+    }
+    let stream = EventStream {
+        receiver: config.change_hub.subscribe(),
+        pending: Cursor::new(format!("retry: {}\n\n", RETRY_MS).into_bytes()),
+        open_streams: Arc::clone(&config.event_streams),
+    };
+    AssetResponse {
+        status: 200,
+        headers: Vec::new(),
+        body: AssetBody::Live(Box::new(stream)),
+    }
+    .with_header("Content-Type", "text/event-stream; charset=utf-8")
+    .with_header("Cache-Control", "no-cache")
+}
+
+/// 把订阅到的变化编码为 SSE 消息，每次 `read` 最多返回一条消息
+struct EventStream {
+    receiver: Receiver<Arc<AssetChanges>>,
+    pending: Cursor<Vec<u8>>,
+    /// 连接关闭、响应体被丢弃时归还名额
+    open_streams: Arc<AtomicUsize>,
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.open_streams.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Read for EventStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pending.position() >= self.pending.get_ref().len() as u64 {
+            let message = match self.receiver.recv_timeout(KEEPALIVE_INTERVAL) {
+                Ok(changes) => format!(
+                    "event: change\ndata: {}\n\n",
+                    serde_json::to_string(&*changes)?
+                ),
+                Err(RecvTimeoutError::Timeout) => ": keepalive\n\n".to_string(),
+                // 服务器停止或切换了根目录
+                Err(RecvTimeoutError::Disconnected) => return Ok(0),
+            };
+            self.pending = Cursor::new(message.into_bytes());
+        }
+        self.pending.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_streams_are_capped() {
+        let config = FileServerConfig::new(String::new());
+        let mut streams: Vec<AssetResponse> = (0..MAX_EVENT_STREAMS)
+            .map(|_| serve_events(&config))
+            .collect();
+        assert!(streams.iter().all(|response| response.status == 200));
+        assert_eq!(serve_events(&config).status, 503);
+
+        streams.pop();
+        assert_eq!(serve_events(&config).status, 200);
+        drop(streams);
+        assert_eq!(config.event_streams.load(Ordering::Relaxed), 0);
+    }
+}
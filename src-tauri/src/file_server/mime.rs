@@ -0,0 +1,251 @@
+use std::path::Path;
+
+/// 内容嗅探时读取的文件头长度
+pub const SNIFF_LEN: usize = 512;
+
+const OCTET_STREAM: &str = "application/octet-stream";
+
+/// 根据扩展名和文件内容判断 MIME 类型
+///
+/// 扩展名无法识别时回退到内容嗅探；图片、音频、视频和字体
+/// 以文件头为准，这样被改错扩展名的素材（如实际是 webp 的 `.png`）也能正常播放。
+/// `read_head` 只在需要嗅探时调用，应返回最多 [`SNIFF_LEN`] 字节的文件头
+pub fn detect_mime_type(path: &Path, read_head: impl FnOnce() -> Option<Vec<u8>>) -> String {
+    let by_extension = mime_from_extension(path);
+    let extension_family = by_extension.and_then(media_family);
+
+    if by_extension.is_none() || extension_family.is_some() {
+        if let Some(sniffed) = read_head().and_then(|head| sniff_mime_type(&head)) {
+            let Some(by_extension) = by_extension else {
+                return sniffed.to_string();
+            };
+            // 媒体文件只在嗅探结果属于同一类（图片、音视频、字体）时覆盖扩展名的判断；
+            // ogg、mp4 等容器既可以装音频也可以装视频，容器相同时保留扩展名的判断
+            if media_family(sniffed) == extension_family
+                && subtype(sniffed) != subtype(by_extension)
+            {
+                return sniffed.to_string();
+            }
+        }
+    }
+
+    by_extension.unwrap_or(OCTET_STREAM).to_string()
+}
+
+/// 媒体类型所属的大类，音频和视频属于同一类；非媒体类型返回 `None`
+fn media_family(mime: &str) -> Option<&'static str> {
+    match mime.split('/').next()? {
+        "image" => Some("image"),
+        "audio" | "video" => Some("av"),
+        "font" => Some("font"),
+        _ => None,
+    }
+}
+
+/// `audio/ogg; codecs=...` 中的 `ogg`
+fn subtype(mime: &str) -> &str {
+    let essence = mime.split(';').next().unwrap_or(mime);
+    essence
+        .split_once('/')
+        .map_or(essence, |(_, subtype)| subtype)
+}
+
+/// 根据扩展名判断 MIME 类型，无法识别时返回 `None`
+pub fn mime_from_extension(path: &Path) -> Option<&'static str> {
+    let ext = path.extension()?.to_string_lossy().to_lowercase();
+    let mime = match ext.as_str() {
+        // 图片
+        "png" | "apng" => "image/png",
+        "jpg" | "jpeg" | "jfif" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        // 音频（bgm、语音、音效）
+        "mp3" => "audio/mpeg",
+        "ogg" | "oga" => "audio/ogg",
+        "opus" => "audio/opus",
+        "wav" => "audio/wav",
+        "flac" => "audio/flac",
+        "m4a" => "audio/mp4",
+        "aac" => "audio/aac",
+        "weba" => "audio/webm",
+        // 视频
+        "webm" => "video/webm",
+        "mp4" | "m4v" => "video/mp4",
+        "ogv" => "video/ogg",
+        "mov" => "video/quicktime",
+        // 字体
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        // 脚本与文本（WebGAL 场景脚本是 .txt）
+        "txt" => "text/plain; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "jsonl" => "application/x-ndjson",
+        // Spine
+        "atlas" => "text/plain; charset=utf-8",
+        "skel" => OCTET_STREAM,
+        // Live2D 二进制文件
+        "moc" | "moc3" => OCTET_STREAM,
+        "physics" | "physics3" => OCTET_STREAM,
+        "motion" | "motion3" => OCTET_STREAM,
+        "expression" | "exp3" | "exp" => OCTET_STREAM,
+        "mtn" => OCTET_STREAM,
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// 根据文件头的魔数判断 MIME 类型
+pub fn sniff_mime_type(head: &[u8]) -> Option<&'static str> {
+    let starts = |magic: &[u8]| head.starts_with(magic);
+    let riff_form = |form: &[u8]| head.len() >= 12 && starts(b"RIFF") && &head[8..12] == form;
+
+    let mime = if starts(b"\x89PNG\r\n\x1a\n") {
+        "image/png"
+    } else if starts(b"\xff\xd8\xff") {
+        "image/jpeg"
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        "image/gif"
+    } else if riff_form(b"WEBP") {
+        "image/webp"
+    } else if starts(b"BM") && head.len() >= 14 {
+        "image/bmp"
+    } else if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        match &head[8..12] {
+            b"avif" | b"avis" => "image/avif",
+            b"M4A " => "audio/mp4",
+            b"qt  " => "video/quicktime",
+            _ => "video/mp4",
+        }
+    } else if starts(b"\x1a\x45\xdf\xa3") {
+        "video/webm"
+    } else if riff_form(b"WAVE") {
+        "audio/wav"
+    } else if starts(b"OggS") {
+        "audio/ogg"
+    } else if starts(b"fLaC") {
+        "audio/flac"
+    } else if head.len() >= 2 && head[0] == 0xff && head[1] & 0xf6 == 0xf0 {
+        // ADTS 与 MPEG 音频帧同步字相同，但 layer 位为 0
+        "audio/aac"
+    } else if starts(b"ID3") || (head.len() >= 2 && head[0] == 0xff && head[1] & 0xe0 == 0xe0) {
+        "audio/mpeg"
+    } else if starts(b"wOFF") {
+        "font/woff"
+    } else if starts(b"wOF2") {
+        "font/woff2"
+    } else if starts(b"OTTO") {
+        "font/otf"
+    } else if starts(b"\x00\x01\x00\x00") {
+        "font/ttf"
+    } else if starts(b"MOC3") || starts(b"moc") {
+        OCTET_STREAM
+    } else {
+        return sniff_text(head);
+    };
+    Some(mime)
+}
+
+/// 文本类内容：JSON、SVG/XML 或普通 UTF-8 文本
+fn sniff_text(head: &[u8]) -> Option<&'static str> {
+    let head = head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head);
+
+    // 文件头可能截断在多字节字符中间，只检查有效的前缀
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+    if text.contains('\0') {
+        return None;
+    }
+
+    let trimmed = text.trim_start();
+    let mime = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        "application/json"
+    } else if trimmed.starts_with("<svg") || (trimmed.starts_with("<?xml") && text.contains("<svg"))
+    {
+        "image/svg+xml"
+    } else if trimmed.starts_with("<?xml") {
+        "application/xml"
+    } else if trimmed
+        .get(..9)
+        .is_some_and(|s| s.eq_ignore_ascii_case("<!doctype"))
+    {
+        "text/html; charset=utf-8"
+    } else {
+        "text/plain; charset=utf-8"
+    };
+    Some(mime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(name: &str, head: &[u8]) -> String {
+        detect_mime_type(Path::new(name), || Some(head.to_vec()))
+    }
+
+    #[test]
+    fn sniff_table() {
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(sniff_mime_type(b"\xff\xd8\xff\xe0"), Some("image/jpeg"));
+        assert_eq!(sniff_mime_type(b"GIF89a"), Some("image/gif"));
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WAVEfmt "), Some("audio/wav"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypisom"), Some("video/mp4"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypM4A "), Some("audio/mp4"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypavif"), Some("image/avif"));
+        assert_eq!(sniff_mime_type(b"\x1a\x45\xdf\xa3"), Some("video/webm"));
+        assert_eq!(sniff_mime_type(b"OggS\0\x02"), Some("audio/ogg"));
+        assert_eq!(sniff_mime_type(b"ID3\x04"), Some("audio/mpeg"));
+        assert_eq!(sniff_mime_type(b"\xff\xfb\x90\x64"), Some("audio/mpeg"));
+        assert_eq!(sniff_mime_type(b"\xff\xf1\x50\x80"), Some("audio/aac"));
+        assert_eq!(sniff_mime_type(b"wOF2"), Some("font/woff2"));
+        assert_eq!(sniff_mime_type(b"{\"a\": 1}"), Some("application/json"));
+        assert_eq!(sniff_mime_type(b"<svg xmlns="), Some("image/svg+xml"));
+        assert_eq!(
+            sniff_mime_type(b"<?xml version=\"1.0\"?>"),
+            Some("application/xml")
+        );
+        assert_eq!(sniff_mime_type(b"hello"), Some("text/plain; charset=utf-8"));
+        assert_eq!(sniff_mime_type(b"\x00\xfe\x81"), None);
+    }
+
+    #[test]
+    fn media_extension_is_overridden_only_within_family() {
+        // 扩展名写错的图片按实际内容返回
+        assert_eq!(detect("a.png", b"RIFF\0\0\0\0WEBPVP8 "), "image/webp");
+        // 容器相同时保留扩展名：ogg 视频、m4a 音频
+        assert_eq!(detect("a.ogv", b"OggS\0\x02"), "video/ogg");
+        assert_eq!(detect("a.m4a", b"\0\0\0\x20ftypmp42"), "audio/mp4");
+        assert_eq!(detect("a.aac", b"\xff\xf1\x50\x80"), "audio/aac");
+        // 文本或 application/* 不会覆盖媒体类型
+        assert_eq!(
+            detect("a.svg", b"<?xml version=\"1.0\"?>\n<!-- long comment"),
+            "image/svg+xml"
+        );
+        assert_eq!(detect("a.png", b"not really a png"), "image/png");
+        // 不同大类之间不覆盖
+        assert_eq!(detect("a.png", b"OggS\0\x02"), "image/png");
+    }
+
+    #[test]
+    fn unknown_extension_falls_back_to_sniffing() {
+        assert_eq!(detect("a.bin", b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(detect("noext", b"{}"), "application/json");
+        assert_eq!(detect_mime_type(Path::new("a.bin"), || None), OCTET_STREAM);
+        // 非媒体扩展名不嗅探
+        assert_eq!(detect("a.json", b"\x89PNG\r\n\x1a\n"), "application/json");
+    }
+}
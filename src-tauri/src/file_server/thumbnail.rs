@@ -0,0 +1,259 @@
+use super::asset::{
+    compute_etag, is_not_modified, resolve_request_source, with_cache_headers, AssetBody,
+    AssetRequest, AssetResponse, AssetSource,
+};
+use super::FileServerConfig;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 缩略图接口前缀：`/__thumb/<path>?w=256`，`<path>` 与普通资源路径相同，同样支持挂载点
+pub const THUMBNAIL_PREFIX: &str = "/__thumb/";
+
+/// 未指定 `w` 时的缩略图宽度
+pub const DEFAULT_THUMBNAIL_WIDTH: u32 = 256;
+
+/// 允许的缩略图宽度范围，超出范围的值会被截断
+pub const MIN_THUMBNAIL_WIDTH: u32 = 16;
+pub const MAX_THUMBNAIL_WIDTH: u32 = 2048;
+
+/// 可以生成缩略图的图片格式，GIF 只取第一帧
+const THUMBNAIL_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "gif"];
+
+/// 缩略图统一编码为 PNG，保留立绘的透明通道
+const THUMBNAIL_MIME: &str = "image/png";
+
+/// 磁盘缓存的大小上限，超出后从最早写入的缩略图开始删除，直到降到上限的四分之三
+const MAX_CACHE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// 默认的缩略图磁盘缓存目录
+pub fn default_thumbnail_dir() -> PathBuf {
+    std::env::temp_dir()
+        .join("webgal-transformeditor")
+        .join("thumbnails")
+}
+
+/// 处理缩略图请求，`url` 是去掉前缀后的路径（可带查询参数）
+pub fn serve_thumbnail(
+    request: &AssetRequest,
+    url: &str,
+    config: &FileServerConfig,
+) -> AssetResponse {
+    let source = match resolve_request_source(url, config) {
+        Ok(source) => source,
+        Err(response) => return response,
+    };
+    let file_path = source.path();
+
+    let supported = file_path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| THUMBNAIL_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false);
+    if !supported {
+        return AssetResponse::text(415, "Unsupported Media Type");
+    }
+
+    let (file_len, modified) = match source.stat() {
+        Ok(stat) => stat,
+        Err(e) => {
+            log::error!("读取文件信息失败: {}", e);
+            return AssetResponse::text(500, &format!("Internal Server Error: {}", e));
+        }
+    };
+    let last_modified = modified.map(httpdate::fmt_http_date);
+
+    // 不同宽度的缩略图是不同的表示，ETag 中带上宽度
+    let width = parse_width(url);
+    let source_etag = compute_etag(file_len, modified);
+    let etag = format!("{}-w{}\"", source_etag.trim_end_matches('"'), width);
+
+    if is_not_modified(request, &etag, modified) {
+        return with_cache_headers(
+            AssetResponse::empty(304),
+            &etag,
+            last_modified.as_deref(),
+            &config.cache_control,
+        );
+    }
+
+    match load_or_create(&config.thumbnail_dir, &source, modified, file_len, width) {
+        Ok(data) => with_cache_headers(
+            AssetResponse {
+                status: 200,
+                headers: Vec::new(),
+                body: AssetBody::Bytes(data),
+            }
+            .with_header("Content-Type", THUMBNAIL_MIME),
+            &etag,
+            last_modified.as_deref(),
+            &config.cache_control,
+        ),
+        Err(e) => {
+            log::error!("生成缩略图失败 {:?}: {}", file_path, e);
+            AssetResponse::text(500, &format!("Internal Server Error: {}", e))
+        }
+    }
+}
+
+/// 从查询参数 `w` 中读取目标宽度
+fn parse_width(url: &str) -> u32 {
+    url.split_once('?')
+        .map(|(_, query)| query)
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == "w")
+        .and_then(|(_, value)| value.parse::<u32>().ok())
+        .unwrap_or(DEFAULT_THUMBNAIL_WIDTH)
+        .clamp(MIN_THUMBNAIL_WIDTH, MAX_THUMBNAIL_WIDTH)
+}
+
+/// 缓存文件名由源文件路径、修改时间、大小和宽度决定，源文件变化后自然失效
+fn cache_path(
+    cache_dir: &Path,
+    file_path: &Path,
+    modified: Option<SystemTime>,
+    file_len: u64,
+    width: u32,
+) -> PathBuf {
+    let mtime = modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let mut hasher = DefaultHasher::new();
+    file_path.hash(&mut hasher);
+    mtime.hash(&mut hasher);
+    file_len.hash(&mut hasher);
+    width.hash(&mut hasher);
+    cache_dir.join(format!("{:016x}-w{}.png", hasher.finish(), width))
+}
+
+/// 读取磁盘缓存；未命中时解码原图、缩放并写回缓存
+fn load_or_create(
+    cache_dir: &Path,
+    source: &AssetSource,
+    modified: Option<SystemTime>,
+    file_len: u64,
+    width: u32,
+) -> io::Result<Vec<u8>> {
+    let file_path = source.path();
+    let cached = cache_path(cache_dir, &file_path, modified, file_len, width);
+    if let Ok(data) = fs::read(&cached) {
+        return Ok(data);
+    }
+
+    let data = render_thumbnail(source, width)?;
+
+    // 先写临时文件再重命名，多个工作线程同时生成同一张缩略图时不会读到半个文件
+    let write_result = fs::create_dir_all(cache_dir).and_then(|_| {
+        let temp = cached.with_extension(format!("{:08x}.tmp", rand::random::<u32>()));
+        fs::write(&temp, &data)?;
+        fs::rename(&temp, &cached).inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })
+    });
+    match write_result {
+        Ok(()) => prune_cache(cache_dir, MAX_CACHE_BYTES),
+        Err(e) => log::warn!("写入缩略图缓存失败 {:?}: {}", cached, e),
+    }
+
+    Ok(data)
+}
+
+/// 缓存超过 `max_bytes` 时按写入时间删除最早的缩略图，切换游戏文件夹后旧文件夹的缩略图随之淘汰
+fn prune_cache(cache_dir: &Path, max_bytes: u64) {
+    let Ok(entries) = fs::read_dir(cache_dir) else {
+        return;
+    };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "png"))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            Some((modified, metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    if total <= max_bytes {
+        return;
+    }
+
+    files.sort();
+    let target = max_bytes / 4 * 3;
+    for (_, len, path) in files {
+        if total <= target {
+            break;
+        }
+        // 其他工作线程可能已经删除了同一个文件
+        if fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+    log::debug!("缩略图缓存已清理到 {} 字节", total);
+}
+
+/// 解码图片并缩放到指定宽度（保持宽高比，不放大），编码为 PNG
+fn render_thumbnail(source: &AssetSource, width: u32) -> io::Result<Vec<u8>> {
+    let image = ImageReader::new(Cursor::new(source.read_all()?))
+        .with_guessed_format()?
+        .decode()
+        .map_err(io::Error::other)?;
+
+    let image = if image.width() > width {
+        image.thumbnail(width, u32::MAX)
+    } else {
+        image
+    };
+
+    // 缩略图统一使用 8 位颜色，16 位和浮点图片先转换
+    let image = match image {
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => image,
+        image if image.color().has_alpha() => DynamicImage::ImageRgba8(image.to_rgba8()),
+        image => DynamicImage::ImageRgb8(image.to_rgb8()),
+    };
+
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+        .map_err(io::Error::other)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn prune_cache_removes_oldest_thumbnails() {
+        let dir = std::env::temp_dir().join(format!("webgal-thumbs-{}", rand::random::<u32>()));
+        fs::create_dir_all(&dir).unwrap();
+        for i in 0..4u64 {
+            let path = dir.join(format!("{}-w256.png", i));
+            fs::write(&path, [0u8; 100]).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(UNIX_EPOCH + Duration::from_secs(1000 + i))
+                .unwrap();
+        }
+        fs::write(dir.join("other.tmp"), [0u8; 100]).unwrap();
+
+        prune_cache(&dir, 400);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 5);
+
+        prune_cache(&dir, 300);
+        let remaining = |name: &str| dir.join(name).exists();
+        assert!(!remaining("0-w256.png"));
+        assert!(!remaining("1-w256.png"));
+        assert!(remaining("2-w256.png"));
+        assert!(remaining("3-w256.png"));
+        assert!(remaining("other.tmp"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
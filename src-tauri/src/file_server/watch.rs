@@ -0,0 +1,401 @@
+use super::archive::{self, ArchiveIndex};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+/// 文件变化时发送给 webview 的 Tauri 事件名
+pub const CHANGE_EVENT: &str = "file-server-changed";
+
+/// 收到文件系统事件后等待的静默时间，导出工具连续写入多个文件时只通知一次
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// 一批文件变化，路径相对于服务器根目录并使用 `/` 分隔
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetChanges {
+    pub changed: Vec<String>,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl AssetChanges {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+type ChangeListener = Box<dyn Fn(&AssetChanges) + Send + Sync>;
+
+/// 把文件变化分发给事件流连接和应用内监听器
+#[derive(Default)]
+pub struct ChangeHub {
+    subscribers: Mutex<Vec<Sender<Arc<AssetChanges>>>>,
+    listener: RwLock<Option<ChangeListener>>,
+}
+
+impl fmt::Debug for ChangeHub {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subscribers = self.subscribers.lock().map(|s| s.len()).unwrap_or(0);
+        f.debug_struct("ChangeHub")
+            .field("subscribers", &subscribers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ChangeHub {
+    /// 订阅之后发生的变化，调用 [`ChangeHub::close_subscribers`] 后接收端断开
+    pub fn subscribe(&self) -> Receiver<Arc<AssetChanges>> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    /// 设置应用内监听器，例如把变化转发为 Tauri 事件
+    pub fn set_listener(&self, listener: impl Fn(&AssetChanges) + Send + Sync + 'static) {
+        if let Ok(mut current) = self.listener.write() {
+            *current = Some(Box::new(listener));
+        }
+    }
+
+    pub fn publish(&self, changes: AssetChanges) {
+        log::debug!(
+            "资源变化: {} 个修改, {} 个新增, {} 个删除",
+            changes.changed.len(),
+            changes.added.len(),
+            changes.removed.len()
+        );
+        if let Ok(listener) = self.listener.read() {
+            if let Some(listener) = listener.as_ref() {
+                listener(&changes);
+            }
+        }
+
+        // 发送失败说明连接已断开，顺便移除
+        let changes = Arc::new(changes);
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|subscriber| subscriber.send(Arc::clone(&changes)).is_ok());
+        }
+    }
+
+    /// 断开所有事件流连接，服务器停止或切换根目录时调用
+    pub fn close_subscribers(&self) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.clear();
+        }
+    }
+}
+
+/// 判断文件内容是否变化的依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fingerprint {
+    /// 磁盘文件的大小和修改时间
+    File(u64, Option<SystemTime>),
+    /// zip 条目的大小和 CRC32
+    Entry(u64, u32),
+}
+
+/// 相对路径 -> 指纹
+type Snapshot = BTreeMap<String, Fingerprint>;
+
+/// 被监视的根目录
+enum WatchRoot {
+    Dir(PathBuf),
+    /// zip 文件，`prefix` 是根目录在包内的路径
+    Archive {
+        path: PathBuf,
+        prefix: String,
+    },
+}
+
+impl WatchRoot {
+    fn new(base_path: &Path) -> io::Result<Self> {
+        match archive::split_archive_path(base_path) {
+            Some((path, prefix)) => Ok(Self::Archive { path, prefix }),
+            // 使用规范路径，与 notify 报告的路径保持一致
+            None => Ok(Self::Dir(base_path.canonicalize()?)),
+        }
+    }
+
+    /// 交给 notify 的路径；zip 被整体替换时文件本身的监视会失效，因此监视其所在目录
+    fn watch_target(&self) -> Option<(&Path, RecursiveMode)> {
+        match self {
+            Self::Dir(dir) => Some((dir.as_path(), RecursiveMode::Recursive)),
+            Self::Archive { path, .. } => Some((path.parent()?, RecursiveMode::NonRecursive)),
+        }
+    }
+
+    /// 把事件涉及的路径转换为需要重新扫描的范围，`""` 表示整个根目录
+    fn scope_of(&self, path: &Path) -> Option<String> {
+        match self {
+            Self::Dir(dir) => relative_path(dir, path),
+            Self::Archive { path: archive, .. } => {
+                (path.file_name() == archive.file_name()).then(String::new)
+            }
+        }
+    }
+
+    /// 扫描 `scope` 下的所有文件，zip 正在写入而无法打开时返回错误；
+    /// `stopped` 被设置后中止扫描并返回 [`io::ErrorKind::Interrupted`]
+    fn scan(&self, scope: &str, stopped: &AtomicBool) -> io::Result<Snapshot> {
+        let mut snapshot = Snapshot::new();
+        match self {
+            Self::Dir(dir) => {
+                let path = dir.join(scope);
+                match fs::symlink_metadata(&path) {
+                    Ok(metadata) if metadata.is_dir() => {
+                        scan_dir(dir, &path, &mut snapshot, stopped)?
+                    }
+                    Ok(_) => {
+                        if let Some(fingerprint) = file_fingerprint(&path) {
+                            snapshot.insert(scope.to_string(), fingerprint);
+                        }
+                    }
+                    Err(_) => {}
+                }
+            }
+            Self::Archive { path, prefix } => {
+                let index = ArchiveIndex::open(path)?;
+                for (name, entry) in index.entries() {
+                    let relative = if prefix.is_empty() {
+                        Some(name)
+                    } else {
+                        name.strip_prefix(prefix.as_str())
+                            .and_then(|rest| rest.strip_prefix('/'))
+                    };
+                    if let Some(relative) = relative.filter(|r| in_scope(r, scope)) {
+                        snapshot.insert(
+                            relative.to_string(),
+                            Fingerprint::Entry(entry.size, entry.crc32),
+                        );
+                    }
+                }
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// 根据一批事件更新快照，返回与上一次快照相比的变化
+    fn apply(
+        &self,
+        snapshot: &mut Snapshot,
+        events: Vec<notify::Result<Event>>,
+        stopped: &AtomicBool,
+    ) -> AssetChanges {
+        let mut scopes = BTreeSet::new();
+        for event in events {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(event) => {
+                    if event.need_rescan() {
+                        scopes.insert(String::new());
+                    }
+                    scopes.extend(event.paths.iter().filter_map(|path| self.scope_of(path)));
+                }
+                Err(e) => {
+                    log::warn!("文件监视出错，重新扫描: {}", e);
+                    scopes.insert(String::new());
+                }
+            }
+        }
+
+        // 已被上级范围包含的范围不需要重复扫描
+        let scopes: Vec<&String> = scopes
+            .iter()
+            .filter(|scope| {
+                !scopes
+                    .iter()
+                    .any(|other| other != *scope && in_scope(scope, other))
+            })
+            .collect();
+
+        let mut changes = AssetChanges::default();
+        for scope in scopes {
+            match self.scan(scope, stopped) {
+                Ok(fresh) => diff_scope(snapshot, scope, fresh, &mut changes),
+                Err(e) => log::debug!("暂时无法扫描 {:?}: {}", scope, e),
+            }
+        }
+        changes.changed.sort();
+        changes.added.sort();
+        changes.removed.sort();
+        changes
+    }
+}
+
+/// `name` 是否位于 `scope` 之内（等于 `scope` 或是其子路径）
+pub(crate) fn in_scope(name: &str, scope: &str) -> bool {
+    scope.is_empty()
+        || name
+            .strip_prefix(scope)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// 用新扫描结果替换快照中 `scope` 范围内的条目，并记录差异
+fn diff_scope(snapshot: &mut Snapshot, scope: &str, fresh: Snapshot, changes: &mut AssetChanges) {
+    let stale: Vec<String> = snapshot
+        .range(scope.to_string()..)
+        .map(|(name, _)| name)
+        .take_while(|name| name.starts_with(scope))
+        .filter(|name| in_scope(name, scope) && !fresh.contains_key(*name))
+        .cloned()
+        .collect();
+    for name in stale {
+        snapshot.remove(&name);
+        changes.removed.push(name);
+    }
+
+    for (name, fingerprint) in fresh {
+        match snapshot.insert(name.clone(), fingerprint) {
+            None => changes.added.push(name),
+            Some(previous) if previous != fingerprint => changes.changed.push(name),
+            Some(_) => {}
+        }
+    }
+}
+
+fn scan_dir(
+    root: &Path,
+    dir: &Path,
+    snapshot: &mut Snapshot,
+    stopped: &AtomicBool,
+) -> io::Result<()> {
+    if stopped.load(Ordering::Relaxed) {
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "文件监视已停止"));
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // 不进入符号链接指向的目录，避免循环
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            scan_dir(root, &path, snapshot, stopped)?;
+        } else if let (Some(name), Some(fingerprint)) =
+            (relative_path(root, &path), file_fingerprint(&path))
+        {
+            snapshot.insert(name, fingerprint);
+        }
+    }
+    Ok(())
+}
+
+fn file_fingerprint(path: &Path) -> Option<Fingerprint> {
+    let metadata = fs::metadata(path).ok()?;
+    metadata
+        .is_file()
+        .then(|| Fingerprint::File(metadata.len(), metadata.modified().ok()))
+}
+
+/// 根目录下路径的 `/` 分隔形式，根目录本身为空字符串
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let segments: Option<Vec<&str>> = relative
+        .components()
+        .map(|component| match component {
+            Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    Some(segments?.join("/"))
+}
+
+/// 监视服务器根目录，把去抖后的变化发布到 [`ChangeHub`]
+pub struct AssetWatcher {
+    base_path: PathBuf,
+    watcher: RecommendedWatcher,
+    /// 停止时设置，正在进行的扫描（包括初始快照）随之中止
+    stopped: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+impl AssetWatcher {
+    /// 开始监视 `base_path`（目录或 zip），初始快照在后台线程中建立
+    pub fn start(base_path: &str, hub: Arc<ChangeHub>) -> Result<Self, String> {
+        let root = WatchRoot::new(Path::new(base_path))
+            .map_err(|e| format!("无法监视 {}: {}", base_path, e))?;
+
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(|e| format!("创建文件监视器失败: {}", e))?;
+        let (target, mode) = root
+            .watch_target()
+            .ok_or_else(|| format!("无法监视 {}", base_path))?;
+        watcher
+            .watch(target, mode)
+            .map_err(|e| format!("无法监视 {}: {}", base_path, e))?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stopped = Arc::clone(&stopped);
+            std::thread::spawn(move || watch_loop(&root, &receiver, &hub, &stopped))
+        };
+        log::info!("开始监视资源变化: {}", base_path);
+        Ok(Self {
+            base_path: PathBuf::from(base_path),
+            watcher,
+            stopped,
+            thread,
+        })
+    }
+
+    /// 是否正在监视 `base_path`，两者指向同一位置时即使写法不同也视为相同
+    pub fn watches(&self, base_path: &Path) -> bool {
+        self.base_path == base_path
+            || matches!(
+                (self.base_path.canonicalize(), base_path.canonicalize()),
+                (Ok(current), Ok(other)) if current == other
+            )
+    }
+
+    /// 停止监视：中止正在进行的扫描，丢弃 notify 监视器会关闭事件通道，监视线程随之退出
+    pub fn stop(self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        drop(self.watcher);
+        if self.thread.join().is_err() {
+            log::error!("文件监视线程异常退出");
+        }
+    }
+}
+
+fn watch_loop(
+    root: &WatchRoot,
+    receiver: &Receiver<notify::Result<Event>>,
+    hub: &ChangeHub,
+    stopped: &AtomicBool,
+) {
+    let mut snapshot = match root.scan("", stopped) {
+        Ok(snapshot) => snapshot,
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => return,
+        Err(_) => Snapshot::new(),
+    };
+
+    while let Ok(first) = receiver.recv() {
+        // 收集事件直到静默 DEBOUNCE 时间
+        let mut events = vec![first];
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(event) => events.push(event),
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+
+        let changes = root.apply(&mut snapshot, events, stopped);
+        if !changes.is_empty() && !stopped.load(Ordering::Relaxed) {
+            hub.publish(changes);
+        }
+    }
+}
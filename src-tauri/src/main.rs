@@ -1,10 +1,10 @@
-use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+mod asset_protocol;
+mod asset_scan;
 mod file_server;
 
-static mut FILE_SERVER_HANDLE: Option<std::thread::JoinHandle<()>> = None;
-static mut FILE_SERVER_PORT: u16 = 0;
-static mut FILE_SERVER_BASE_PATH: Option<String> = None;
+use file_server::archive::AssetFs;
+use file_server::{FileServerState, Transport};
 
 #[tauri::command]
 fn get_asset_path() -> String {
@@ -72,48 +72,121 @@ async fn open_script_output_window(app: tauri::AppHandle) -> Result<(), String>
 }
 
 #[tauri::command]
-fn start_local_server(base_path: String) -> Result<String, String> {
-    unsafe {
-        // 如果服务器已经在运行，先停止它
-        if let Some(handle) = FILE_SERVER_HANDLE.take() {
-            handle.thread().unpark();
-        }
-        
-        // 查找可用端口
-        let port = find_available_port(8000).ok_or("找不到可用端口")?;
-        FILE_SERVER_PORT = port;
-        FILE_SERVER_BASE_PATH = Some(base_path.clone());
-        
-        // 启动文件服务器
-        if let Some(handle) = file_server::start_file_server(port, base_path) {
-            FILE_SERVER_HANDLE = Some(handle);
-            Ok(format!("http://127.0.0.1:{}", port))
-        } else {
-            Err("启动文件服务器失败".to_string())
-        }
+async fn start_local_server(
+    app: tauri::AppHandle,
+    base_path: String,
+    cache_control: Option<String>,
+    worker_count: Option<usize>,
+    transport: Option<Transport>,
+) -> Result<file_server::LocalServerInfo, String> {
+    use tauri::Manager;
+
+    let mut config = file_server::FileServerConfig::new(base_path);
+    if let Some(cache_control) = cache_control {
+        config.cache_control = cache_control;
+    }
+    if let Some(worker_count) = worker_count {
+        config.worker_count = worker_count;
     }
+    if let Ok(cache_dir) = app.path().app_cache_dir() {
+        config.thumbnail_dir = cache_dir.join("thumbnails");
+    }
+
+    // 如果服务器已经在运行，start 会先停止它并释放端口；停止可能需要等待线程退出，不在主线程执行
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<FileServerState>()
+            .start(config, transport.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| format!("启动文件服务器失败: {}", e))?
 }
 
-fn find_available_port(start_port: u16) -> Option<u16> {
-    use std::net::TcpListener;
-    for port in start_port..=start_port + 100 {
-        if TcpListener::bind(format!("127.0.0.1:{}", port)).is_ok() {
-            return Some(port);
-        }
-    }
-    None
+#[tauri::command]
+async fn stop_local_server(app: tauri::AppHandle) -> Result<bool, String> {
+    use tauri::Manager;
+
+    tauri::async_runtime::spawn_blocking(move || app.state::<FileServerState>().stop())
+        .await
+        .map_err(|e| format!("停止文件服务器失败: {}", e))?
 }
 
 #[tauri::command]
-fn extract_jsonl_motions_expressions(file_path: String, game_folder: Option<String>) -> Result<serde_json::Value, String> {
-    use serde_json::{json, Value};
-    
-    // 构造完整文件路径
-    let full_path = if let Some(game_folder) = game_folder {
+fn add_local_server_mount(
+    state: tauri::State<'_, FileServerState>,
+    name: String,
+    path: String,
+) -> Result<file_server::MountInfo, String> {
+    state.add_mount(name, path)
+}
+
+#[tauri::command]
+fn remove_local_server_mount(
+    state: tauri::State<'_, FileServerState>,
+    name: String,
+) -> Result<bool, String> {
+    state.remove_mount(&name)
+}
+
+#[tauri::command]
+fn list_local_server_mounts(
+    state: tauri::State<'_, FileServerState>,
+) -> Result<Vec<file_server::MountInfo>, String> {
+    state.list_mounts()
+}
+
+#[tauri::command]
+fn set_local_server_allowed_origins(
+    state: tauri::State<'_, FileServerState>,
+    origins: Vec<String>,
+) -> Result<Vec<String>, String> {
+    state.set_allowed_origins(origins)
+}
+
+#[tauri::command]
+fn get_local_server_allowed_origins(
+    state: tauri::State<'_, FileServerState>,
+) -> Result<Vec<String>, String> {
+    state.allowed_origins()
+}
+
+/// 默认返回的访问记录条数
+const DEFAULT_ACCESS_LOG_LIMIT: usize = 100;
+
+#[tauri::command]
+fn get_local_server_access_log(
+    state: tauri::State<'_, FileServerState>,
+    limit: Option<usize>,
+) -> Vec<file_server::access_log::AccessLogEntry> {
+    state.recent_access_log(limit.unwrap_or(DEFAULT_ACCESS_LOG_LIMIT))
+}
+
+#[tauri::command]
+fn clear_local_server_access_log(state: tauri::State<'_, FileServerState>) {
+    state.clear_access_log();
+}
+
+#[tauri::command]
+fn set_local_server_access_log_level(
+    state: tauri::State<'_, FileServerState>,
+    level: String,
+) -> Result<String, String> {
+    state.set_access_log_level(&level)
+}
+
+#[tauri::command]
+fn get_local_server_status(
+    state: tauri::State<'_, FileServerState>,
+) -> Result<file_server::FileServerStatus, String> {
+    state.status()
+}
+
+/// 立绘文件的完整路径：提供游戏文件夹时，相对路径以 game/figure 为基准
+fn figure_file_path(file_path: &str, game_folder: Option<String>) -> PathBuf {
+    if let Some(game_folder) = game_folder {
         // 如果提供了游戏文件夹，将相对路径转换为绝对路径
         let game_path = Path::new(&game_folder);
-        let file_path_obj = Path::new(&file_path);
-        
+        let file_path_obj = Path::new(file_path);
+
         // 如果 file_path 已经是绝对路径，直接使用
         if file_path_obj.is_absolute() {
             file_path_obj.to_path_buf()
@@ -124,24 +197,66 @@ fn extract_jsonl_motions_expressions(file_path: String, game_folder: Option<Stri
         }
     } else {
         // 如果没有游戏文件夹，假设 file_path 是绝对路径
-        Path::new(&file_path).to_path_buf()
-    };
+        Path::new(file_path).to_path_buf()
+    }
+}
+
+/// 读取模型（或 JSONL 各子模型）中每个动作的时长、帧率、循环、淡入淡出和曲线目标
+#[tauri::command]
+async fn get_motion_timings(
+    file_path: String,
+    game_folder: Option<String>,
+) -> Result<Vec<asset_scan::motion::ModelMotion>, String> {
+    let full_path = figure_file_path(&file_path, game_folder);
+    tauri::async_runtime::spawn_blocking(move || {
+        let asset_fs = AssetFs::for_path(&full_path).map_err(|e| format!("读取 zip 失败: {}", e))?;
+        if !asset_fs.is_file(&full_path) {
+            return Err(format!("文件不存在: {:?}", full_path));
+        }
+        asset_scan::motion::model_motions(&asset_fs, &full_path)
+    })
+    .await
+    .map_err(|e| format!("读取动作任务失败: {}", e))?
+}
+
+/// 提取动作和表情列表；merge_sub_models 为 true 时，JSONL 还会合并每个子模型中的动作和表情，
+/// 并在 motionSources/expressionSources 中标注提供每个名称的子模型，failedSubModels 列出无法读取的子模型
+#[tauri::command]
+async fn extract_jsonl_motions_expressions(file_path: String, game_folder: Option<String>, merge_sub_models: Option<bool>) -> Result<serde_json::Value, String> {
+    // 构造完整文件路径
+    let full_path = figure_file_path(&file_path, game_folder);
+    tauri::async_runtime::spawn_blocking(move || {
+        read_motions_expressions(&full_path, merge_sub_models.unwrap_or(false))
+    })
+    .await
+    .map_err(|e| format!("读取动作和表情任务失败: {}", e))?
+}
+
+/// 读取模型文件中的动作和表情，路径可以位于 zip 内
+fn read_motions_expressions(full_path: &Path, merge_sub_models: bool) -> Result<serde_json::Value, String> {
+    use serde_json::{json, Value};
+    
+    let asset_fs = AssetFs::for_path(full_path).map_err(|e| format!("读取 zip 失败: {}", e))?;
     
     // 检查文件是否存在
-    if !full_path.exists() {
+    if !asset_fs.exists(full_path) {
         return Err(format!("文件不存在: {:?}", full_path));
     }
     
-    if !full_path.is_file() {
+    if !asset_fs.is_file(full_path) {
         return Err(format!("路径不是文件: {:?}", full_path));
     }
     
     // 读取文件内容
-    let content = fs::read_to_string(&full_path)
+    let content = asset_fs.read_to_string(full_path)
         .map_err(|e| format!("读取文件失败: {}", e))?;
     
     let mut motions: Vec<String> = Vec::new();
     let mut expressions: Vec<String> = Vec::new();
+    let mut motion_groups: Vec<asset_scan::live2d::MotionGroup> = Vec::new();
+    let mut motion_sources: Vec<asset_scan::live2d::NameSource> = Vec::new();
+    let mut expression_sources: Vec<asset_scan::live2d::NameSource> = Vec::new();
+    let mut failed_sub_models: Vec<String> = Vec::new();
     
     // 根据文件扩展名判断是 JSON 还是 JSONL
     let ext = full_path.extension()
@@ -153,51 +268,16 @@ fn extract_jsonl_motions_expressions(file_path: String, game_folder: Option<Stri
         // 解析 JSON 文件（整个文件是一个 JSON 对象）
         match serde_json::from_str::<Value>(&content) {
             Ok(obj) => {
-                // 提取 motions
-                if let Some(motions_val) = obj.get("motions") {
-                    if let Some(motions_arr) = motions_val.as_array() {
-                        // 如果是数组，提取字符串或对象的 name/file/id
-                        for m in motions_arr {
-                            if let Some(m_str) = m.as_str() {
-                                motions.push(m_str.to_string());
-                            } else if let Some(m_obj) = m.as_object() {
-                                // 如果是对象，尝试提取 name/file/id 字段
-                                if let Some(name) = m_obj.get("name").and_then(|n| n.as_str()) {
-                                    motions.push(name.to_string());
-                                } else if let Some(file) = m_obj.get("file").and_then(|f| f.as_str()) {
-                                    motions.push(file.to_string());
-                                } else if let Some(id) = m_obj.get("id").and_then(|i| i.as_str()) {
-                                    motions.push(id.to_string());
-                                }
-                            }
-                        }
-                    } else if let Some(motions_obj) = motions_val.as_object() {
-                        // 如果是对象，提取所有键名
-                        motions = motions_obj.keys().map(|k| k.to_string()).collect();
-                    }
-                }
-                
-                // 提取 expressions
-                if let Some(expressions_val) = obj.get("expressions") {
-                    if let Some(expressions_arr) = expressions_val.as_array() {
-                        // 如果是数组，提取字符串或对象的 name/file/id
-                        for e in expressions_arr {
-                            if let Some(e_str) = e.as_str() {
-                                expressions.push(e_str.to_string());
-                            } else if let Some(e_obj) = e.as_object() {
-                                // 如果是对象，尝试提取 name/file/id 字段
-                                if let Some(name) = e_obj.get("name").and_then(|n| n.as_str()) {
-                                    expressions.push(name.to_string());
-                                } else if let Some(file) = e_obj.get("file").and_then(|f| f.as_str()) {
-                                    expressions.push(file.to_string());
-                                } else if let Some(id) = e_obj.get("id").and_then(|i| i.as_str()) {
-                                    expressions.push(id.to_string());
-                                }
-                            }
-                        }
-                    } else if let Some(expressions_obj) = expressions_val.as_object() {
-                        // 如果是对象，提取所有键名
-                        expressions = expressions_obj.keys().map(|k| k.to_string()).collect();
+                // 提取 motions 和 expressions
+                motions = asset_scan::live2d::declared_names(obj.get("motions"));
+                expressions = asset_scan::live2d::declared_names(obj.get("expressions"));
+
+                // Cubism 3/4：动作在 FileReferences.Motions 分组中，表情在 FileReferences.Expressions[].Name
+                if let Some(manifest) = asset_scan::live2d::resolve(&obj) {
+                    motion_groups = manifest.motion_groups();
+                    if manifest.generation == asset_scan::live2d::Generation::Cubism3 {
+                        motions = motion_groups.iter().map(|group| group.name.clone()).collect();
+                        expressions = manifest.expression_names();
                     }
                 }
             }
@@ -215,51 +295,9 @@ fn extract_jsonl_motions_expressions(file_path: String, game_folder: Option<Stri
                 Ok(obj) => {
                     // 检查是否是汇总行（包含 motions 或 expressions）
                     if obj.get("motions").is_some() || obj.get("expressions").is_some() {
-                        // 提取 motions
-                        if let Some(motions_val) = obj.get("motions") {
-                            if let Some(motions_arr) = motions_val.as_array() {
-                                for m in motions_arr {
-                                    if let Some(m_str) = m.as_str() {
-                                        motions.push(m_str.to_string());
-                                    } else if let Some(m_obj) = m.as_object() {
-                                        // 如果是对象，尝试提取 name/file/id 字段
-                                        if let Some(name) = m_obj.get("name").and_then(|n| n.as_str()) {
-                                            motions.push(name.to_string());
-                                        } else if let Some(file) = m_obj.get("file").and_then(|f| f.as_str()) {
-                                            motions.push(file.to_string());
-                                        } else if let Some(id) = m_obj.get("id").and_then(|i| i.as_str()) {
-                                            motions.push(id.to_string());
-                                        }
-                                    }
-                                }
-                            } else if let Some(motions_obj) = motions_val.as_object() {
-                                // 如果是对象，提取所有键名
-                                motions = motions_obj.keys().map(|k| k.to_string()).collect();
-                            }
-                        }
-                        
-                        // 提取 expressions
-                        if let Some(expressions_val) = obj.get("expressions") {
-                            if let Some(expressions_arr) = expressions_val.as_array() {
-                                for e in expressions_arr {
-                                    if let Some(e_str) = e.as_str() {
-                                        expressions.push(e_str.to_string());
-                                    } else if let Some(e_obj) = e.as_object() {
-                                        // 如果是对象，尝试提取 name/file/id 字段
-                                        if let Some(name) = e_obj.get("name").and_then(|n| n.as_str()) {
-                                            expressions.push(name.to_string());
-                                        } else if let Some(file) = e_obj.get("file").and_then(|f| f.as_str()) {
-                                            expressions.push(file.to_string());
-                                        } else if let Some(id) = e_obj.get("id").and_then(|i| i.as_str()) {
-                                            expressions.push(id.to_string());
-                                        }
-                                    }
-                                }
-                            } else if let Some(expressions_obj) = expressions_val.as_object() {
-                                // 如果是对象，提取所有键名
-                                expressions = expressions_obj.keys().map(|k| k.to_string()).collect();
-                            }
-                        }
+                        // 提取 motions 和 expressions
+                        motions = asset_scan::live2d::declared_names(obj.get("motions"));
+                        expressions = asset_scan::live2d::declared_names(obj.get("expressions"));
                         
                         // 找到汇总行后就可以返回了
                         break;
@@ -271,237 +309,175 @@ fn extract_jsonl_motions_expressions(file_path: String, game_folder: Option<Stri
                 }
             }
         }
+
+        // 汇总行之外，再合并各子模型自己的动作和表情
+        if merge_sub_models {
+            let sub_models = asset_scan::live2d::jsonl_sub_models(&asset_fs, full_path);
+            motion_sources = asset_scan::live2d::merge_names(&motions, &sub_models, |manifest| {
+                manifest.motion_groups().into_iter().map(|group| group.name).collect()
+            });
+            expression_sources = asset_scan::live2d::merge_names(&expressions, &sub_models, |manifest| {
+                manifest.expression_names()
+            });
+            motions = motion_sources.iter().map(|source| source.name.clone()).collect();
+            expressions = expression_sources.iter().map(|source| source.name.clone()).collect();
+            failed_sub_models = sub_models.failed;
+        }
     }
     
     Ok(json!({
         "motions": motions,
-        "expressions": expressions
+        "expressions": expressions,
+        "motionGroups": motion_groups,
+        "motionSources": motion_sources,
+        "expressionSources": expression_sources,
+        "failedSubModels": failed_sub_models
     }))
 }
 
-#[tauri::command]
-fn scan_directory_recursive(dir_path: String) -> Result<Vec<String>, String> {
-    let path = Path::new(&dir_path);
+/// 打开要扫描的目录，路径可以指向 zip 文件或 zip 内的目录，此时从包内枚举文件
+fn open_scan_dir(dir_path: &str) -> Result<AssetFs, String> {
+    let path = Path::new(dir_path);
+    let asset_fs = AssetFs::for_path(path).map_err(|e| format!("读取 zip 失败: {}", e))?;
     
-    if !path.exists() {
+    if !asset_fs.exists(path) {
         return Err(format!("路径不存在: {}", dir_path));
     }
     
-    if !path.is_dir() {
+    if !asset_fs.is_dir(path) {
         return Err(format!("路径不是目录: {}", dir_path));
     }
     
-    let mut files = Vec::new();
-    let mut excluded_files = std::collections::HashSet::new();
-    
-    fn walk_dir(dir: &Path, base_dir: &Path, files: &mut Vec<String>, excluded_files: &mut std::collections::HashSet<String>) -> Result<(), String> {
-        // 先收集并排序：确保 jsonl/json 先于 png 等被处理，从而先填充 excluded_files
-        let mut entries: Vec<std::path::PathBuf> = Vec::new();
-        for entry in fs::read_dir(dir).map_err(|e| format!("读取目录失败: {}", e))? {
-            let entry = entry.map_err(|e| format!("读取条目失败: {}", e))?;
-            entries.push(entry.path());
-        }
-        entries.sort_by(|a, b| {
-            let a_ext = a.extension().and_then(|e| Some(e.to_string_lossy().to_lowercase())).unwrap_or_default();
-            let b_ext = b.extension().and_then(|e| Some(e.to_string_lossy().to_lowercase())).unwrap_or_default();
-            let a_rank = if a_ext == "jsonl" || a_ext == "json" { 0 } else { 1 };
-            let b_rank = if b_ext == "jsonl" || b_ext == "json" { 0 } else { 1 };
-            a_rank.cmp(&b_rank)
-        });
-        
-        for path in entries.into_iter() {
-            
-            if path.is_dir() {
-                walk_dir(&path, base_dir, files, excluded_files)?;
-            } else if let Some(ext) = path.extension() {
-                let ext_lower = ext.to_string_lossy().to_lowercase();
-                
-                // 处理 JSONL 聚合模型文件
-                if ext_lower.as_str() == "jsonl" {
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        // 解析 JSONL，每行一个 JSON 对象
-                        for line in content.lines() {
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(line) {
-                                if let Some(path_str) = json.get("path").and_then(|p| p.as_str()) {
-                                    // 获取父目录
-                                    if let Some(parent_dir) = path.parent() {
-                                        let sub_model_path = parent_dir.join(path_str);
-                                        if let Ok(relative_path) = sub_model_path.strip_prefix(base_dir) {
-                                            let relative_str = relative_path.to_string_lossy().replace('\\', "/");
-                                            excluded_files.insert(relative_str);
-                                            
-                                            // 也读取该子模型，提取 textures
-                                            if sub_model_path.exists() && sub_model_path.is_file() {
-                                                if let Ok(sub_content) = fs::read_to_string(&sub_model_path) {
-                                                    if let Ok(sub_json) = serde_json::from_str::<serde_json::Value>(&sub_content) {
-                                                        if let Some(textures) = sub_json.get("textures").and_then(|t| t.as_array()) {
-                                                            for texture in textures {
-                                                                if let Some(tex_path) = texture.as_str() {
-                                                                    // 纹理路径以子模型 json 所在目录为基准
-                                                                    let tex_base = sub_model_path.parent().unwrap_or(parent_dir);
-                                                                    let tex_full_path = tex_base.join(tex_path);
-                                                                    if let Ok(tex_relative) = tex_full_path.strip_prefix(base_dir) {
-                                                                        excluded_files.insert(tex_relative.to_string_lossy().replace('\\', "/"));
-                                                                    }
-                                                                }
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    // JSONL 文件本身应该包含在结果中
-                    let relative_path = path.strip_prefix(base_dir)
-                        .map_err(|e| format!("计算相对路径失败: {}", e))?;
-                    files.push(relative_path.to_string_lossy().replace('\\', "/"));
-                }
-                // 对于普通 JSON 文件，检查是否包含 Live2D 模型字段
-                else if ext_lower.as_str() == "json" {
-                    // 默认先根据文件名判断，增加鲁棒性
-                    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                    let looks_like_model = file_name.to_lowercase().contains(".char.json") || 
-                                         file_name.to_lowercase().contains("model.json");
-                    
-                    let mut is_live2d_file = looks_like_model;
-                    let mut is_mano_file = looks_like_model;
-                    let parent_dir = path.parent();
-
-                    if let Ok(content) = fs::read_to_string(&path) {
-                        if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                            // 检查是否包含 model, textures, motions 字段（Cubism 2 格式）
-                            if json.get("model").is_some() || 
-                               json.get("textures").is_some() || 
-                               json.get("motions").is_some() {
-                                is_live2d_file = true;
-                                // ... (textures extraction code continues)
-                                
-                                // 提取 textures 并加入排除列表
-                                if let Some(textures) = json.get("textures").and_then(|t| t.as_array()) {
-                                    for texture in textures {
-                                        if let Some(tex_path) = texture.as_str() {
-                                            // 使用 json 文件的父目录作为基准
-                                            if let Some(parent) = parent_dir {
-                                                let tex_full_path = parent.join(tex_path);
-                                                if let Ok(tex_relative) = tex_full_path.strip_prefix(base_dir) {
-                                                    excluded_files.insert(tex_relative.to_string_lossy().replace('\\', "/"));
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            // 检查是否包含 Version 和 FileReferences 字段（Cubism 3/4 格式）
-                            else if json.get("Version").is_some() && json.get("FileReferences").is_some() {
-                                is_live2d_file = true;
-                                
-                                // 提取 FileReferences 中的文件引用并加入排除列表
-                                if let Some(file_refs) = json.get("FileReferences").and_then(|fr| fr.as_object()) {
-                                    // 提取 Textures 数组中的贴图文件
-                                    if let Some(textures) = file_refs.get("Textures").and_then(|t| t.as_array()) {
-                                        for texture in textures {
-                                            if let Some(tex_path) = texture.as_str() {
-                                                if let Some(parent) = parent_dir {
-                                                    let tex_full_path = parent.join(tex_path);
-                                                    if let Ok(tex_relative) = tex_full_path.strip_prefix(base_dir) {
-                                                        excluded_files.insert(tex_relative.to_string_lossy().replace('\\', "/"));
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                    
-                                    // 提取 Physics 文件（如果存在）
-                                    if let Some(physics) = file_refs.get("Physics").and_then(|p| p.as_str()) {
-                                        if let Some(parent) = parent_dir {
-                                            let physics_full_path = parent.join(physics);
-                                            if let Ok(physics_relative) = physics_full_path.strip_prefix(base_dir) {
-                                                excluded_files.insert(physics_relative.to_string_lossy().replace('\\', "/"));
-                                            }
-                                        }
-                                    }
-                                    
-                                    // 提取 DisplayInfo 文件（如果存在）
-                                    if let Some(display_info) = file_refs.get("DisplayInfo").and_then(|d| d.as_str()) {
-                                        if let Some(parent) = parent_dir {
-                                            let display_info_full_path = parent.join(display_info);
-                                            if let Ok(display_info_relative) = display_info_full_path.strip_prefix(base_dir) {
-                                                excluded_files.insert(display_info_relative.to_string_lossy().replace('\\', "/"));
-                                            }
-                                        }
-                                    }
-                                    
-                                    // 提取 Moc 文件（如果存在）
-                                    if let Some(moc) = file_refs.get("Moc").and_then(|m| m.as_str()) {
-                                        if let Some(parent) = parent_dir {
-                                            let moc_full_path = parent.join(moc);
-                                            if let Ok(moc_relative) = moc_full_path.strip_prefix(base_dir) {
-                                                excluded_files.insert(moc_relative.to_string_lossy().replace('\\', "/"));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            // 检查是否包含 settings, assets, controller 字段 (Mano 格式)
-                            // 兼容 "setting" 或 "settings"
-                            if json.get("settings").is_some() || 
-                                    json.get("setting").is_some() || 
-                                    json.get("assets").is_some() || 
-                                    json.get("controller").is_some() {
-                                is_mano_file = true;
-                                
-                                // 提取 assets 中的 layers 并加入排除列表
-                                if let Some(assets) = json.get("assets") {
-                                    if let Some(layers) = assets.get("layers").and_then(|l| l.as_array()) {
-                                        for layer in layers {
-                                            if let Some(layer_path) = layer.get("path").and_then(|p| p.as_str()) {
-                                                // 使用 json 文件的父目录作为基准
-                                                if let Some(parent) = parent_dir {
-                                                    let img_full_path = parent.join(layer_path);
-                                                    if let Ok(img_relative) = img_full_path.strip_prefix(base_dir) {
-                                                        excluded_files.insert(img_relative.to_string_lossy().replace('\\', "/"));
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            
-                            // 如果是 Live2D 或 Mano 文件，添加到结果列表中
-                            if is_live2d_file || is_mano_file {
-                                let relative_path = path.strip_prefix(base_dir)
-                                    .map_err(|e| format!("计算相对路径失败: {}", e))?;
-                                let relative_str = relative_path.to_string_lossy().replace('\\', "/");
-                                
-                                // 只添加未被排除的文件
-                                if !excluded_files.contains(&relative_str) {
-                                    files.push(relative_str);
-                                }
-                            }
-                        }
-                    }
-                } else if ["png", "jpg", "jpeg", "gif", "bmp", "webp", "webm"].contains(&ext_lower.as_str()) {
-                    // 其他文件类型直接添加（但也要排除）
-                    let relative_path = path.strip_prefix(base_dir)
-                        .map_err(|e| format!("计算相对路径失败: {}", e))?;
-                    let relative_str = relative_path.to_string_lossy().replace('\\', "/");
-                    // 只添加未被排除的文件
-                    if !excluded_files.contains(&relative_str) {
-                        files.push(relative_str);
-                    }
-                }
+    Ok(asset_fs)
+}
+
+/// 扫描索引保存在应用数据目录中，清理缓存不会让下次扫描退回完整解析
+fn asset_index_dir(app: &tauri::AppHandle) -> Option<PathBuf> {
+    use tauri::Manager;
+
+    app.path()
+        .app_data_dir()
+        .ok()
+        .map(|data_dir| data_dir.join("asset-index"))
+}
+
+/// 在后台线程上执行扫描；提供 `scan_id` 时发送进度事件，并可通过 cancel_directory_scan 取消
+async fn run_directory_scan<T: Send + 'static>(
+    app: tauri::AppHandle,
+    scans: &asset_scan::ScanRegistry,
+    dir_path: String,
+    scan_id: Option<String>,
+    scan: fn(&AssetFs, &Path, &asset_scan::ScanControl) -> Result<T, String>,
+) -> Result<T, String> {
+    use tauri::Emitter;
+
+    let index_dir = asset_index_dir(&app);
+    let cancelled = match &scan_id {
+        Some(scan_id) => scans.begin(scan_id),
+        None => Default::default(),
+    };
+    let control = match &scan_id {
+        Some(_) => asset_scan::ScanControl::new(scan_id.clone(), cancelled.clone(), move |progress| {
+            let _ = app.emit(asset_scan::SCAN_PROGRESS_EVENT, progress);
+        }),
+        None => asset_scan::ScanControl::default(),
+    };
+    let control = match index_dir {
+        Some(index_dir) => control.with_index_dir(index_dir),
+        None => control,
+    };
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        let asset_fs = open_scan_dir(&dir_path)?;
+        scan(&asset_fs, Path::new(&dir_path), &control)
+    })
+    .await;
+
+    // 扫描任务异常退出时同样移除登记
+    if let Some(scan_id) = &scan_id {
+        scans.finish(scan_id, &cancelled);
+    }
+    result.map_err(|e| format!("扫描任务失败: {}", e))?
+}
+
+#[tauri::command]
+async fn scan_directory_recursive(
+    app: tauri::AppHandle,
+    scans: tauri::State<'_, asset_scan::ScanRegistry>,
+    dir_path: String,
+    scan_id: Option<String>,
+) -> Result<Vec<String>, String> {
+    run_directory_scan(app, &scans, dir_path, scan_id, asset_scan::scan_paths).await
+}
+
+/// 与 scan_directory_recursive 相同的扫描，额外返回类型、大小、修改时间、图片尺寸和被归入的依赖文件
+#[tauri::command]
+async fn scan_directory_detailed(
+    app: tauri::AppHandle,
+    scans: tauri::State<'_, asset_scan::ScanRegistry>,
+    dir_path: String,
+    scan_id: Option<String>,
+) -> Result<Vec<asset_scan::AssetEntry>, String> {
+    run_directory_scan(app, &scans, dir_path, scan_id, asset_scan::scan_entries).await
+}
+
+/// 取消正在进行的扫描，被取消的扫描返回错误 "扫描已取消"
+#[tauri::command]
+fn cancel_directory_scan(
+    scans: tauri::State<'_, asset_scan::ScanRegistry>,
+    scan_id: String,
+) -> bool {
+    scans.cancel(&scan_id)
+}
+
+/// 校验立绘（Live2D 模型、JSONL 或 Mano）引用的文件，报告缺失、JSON 错误、大小写不一致和无法播放的动作/表情
+#[tauri::command]
+async fn validate_figure(path: String) -> Result<asset_scan::validate::FigureReport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let file_path = Path::new(&path);
+        let asset_fs = AssetFs::for_path(file_path).map_err(|e| format!("读取 zip 失败: {}", e))?;
+        asset_scan::validate::validate_figure(&asset_fs, file_path)
+    })
+    .await
+    .map_err(|e| format!("校验任务失败: {}", e))?
+}
+
+/// 监视游戏的立绘和背景文件夹，素材增减时发送 assets-changed 事件；重复调用会替换之前的监视
+///
+/// 文件服务器正在监视同一游戏文件夹时复用它的文件变化，不重复监视
+#[tauri::command]
+async fn watch_game_assets(app: tauri::AppHandle, game_folder: String) -> Result<(), String> {
+    use tauri::{Emitter, Manager};
+
+    let index_dir = asset_index_dir(&app);
+
+    // 建立监视和停止之前的监视都可能需要等待扫描结束，不在主线程执行
+    tauri::async_runtime::spawn_blocking(move || {
+        let changes = app
+            .state::<FileServerState>()
+            .subscribe_changes(Path::new(&game_folder));
+        let emitter = app.clone();
+        let watcher = asset_scan::watch::GameAssetWatcher::start(&game_folder, changes, index_dir, move |changes| {
+            if let Err(e) = emitter.emit(asset_scan::watch::ASSETS_CHANGED_EVENT, changes) {
+                log::warn!("发送素材变化事件失败: {}", e);
             }
-        }
+        })?;
+        app.state::<asset_scan::watch::GameAssetWatchState>().replace(Some(watcher));
         Ok(())
-    }
-    
-    walk_dir(path, path, &mut files, &mut excluded_files)?;
-    Ok(files)
+    })
+    .await
+    .map_err(|e| format!("监视素材文件夹失败: {}", e))?
+}
+
+#[tauri::command]
+async fn unwatch_game_assets(app: tauri::AppHandle) -> Result<(), String> {
+    use tauri::Manager;
+
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<asset_scan::watch::GameAssetWatchState>().replace(None);
+    })
+    .await
+    .map_err(|e| format!("停止监视素材文件夹失败: {}", e))
 }
 
 fn main() {
@@ -510,7 +486,26 @@ fn main() {
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_log::Builder::default().build())
-        .invoke_handler(tauri::generate_handler![get_asset_path, scan_directory_recursive, start_local_server, open_filter_editor_window, open_script_output_window, extract_jsonl_motions_expressions])
+        .manage(FileServerState::default())
+        .manage(asset_scan::ScanRegistry::default())
+        .manage(asset_scan::watch::GameAssetWatchState::default())
+        .setup(|app| {
+            use tauri::{Emitter, Manager};
+
+            // 把资源变化转发给 webview，资源协议模式下无法使用事件流时也能收到通知
+            let handle = app.handle().clone();
+            app.state::<FileServerState>().set_change_listener(move |changes| {
+                if let Err(e) = handle.emit(file_server::watch::CHANGE_EVENT, changes) {
+                    log::warn!("发送资源变化事件失败: {}", e);
+                }
+            });
+            Ok(())
+        })
+        .register_asynchronous_uri_scheme_protocol(
+            file_server::ASSET_PROTOCOL,
+            asset_protocol::handle_asset_protocol,
+        )
+        .invoke_handler(tauri::generate_handler![get_asset_path, scan_directory_recursive, scan_directory_detailed, cancel_directory_scan, watch_game_assets, unwatch_game_assets, validate_figure, get_motion_timings, start_local_server, stop_local_server, get_local_server_status, add_local_server_mount, remove_local_server_mount, list_local_server_mounts, set_local_server_allowed_origins, get_local_server_allowed_origins, get_local_server_access_log, clear_local_server_access_log, set_local_server_access_log_level, open_filter_editor_window, open_script_output_window, extract_jsonl_motions_expressions])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -1,16 +1,35 @@
+/** Live2D 模型的一个动作分组，index 是动作在分组中的序号 */
+export interface MotionGroup {
+  name: string;
+  motions: { index: number; file: string }[];
+}
+
+/** 合并 JSONL 子模型后的动作/表情名称，partial 表示只有部分子模型提供 */
+export interface NameSource {
+  name: string;
+  subModels: string[];
+  partial: boolean;
+}
+
 /**
  * 从 JSONL 或 JSON 文件中提取 motions 和 expressions 列表
  * 使用后端 Rust 代码读取文件，避免路径转换问题
  * @param filePath JSONL 或 JSON 文件路径（相对路径，相对于游戏文件夹的 figure 目录）
  * @param gameFolder 可选的游戏文件夹路径（如果不提供，会尝试从 webgalFileManager 获取）
- * @returns 包含 motions 和 expressions 数组的对象
+ * @param mergeSubModels 为 true 时 JSONL 还会合并每个子模型中的动作和表情，并返回提供每个名称的子模型和无法读取的子模型
+ * @returns 包含 motions 和 expressions 数组的对象；Live2D 模型还会返回按分组列出的动作
  */
 export async function extractMotionsAndExpressions(
   filePath: string,
-  gameFolder?: string | null
+  gameFolder?: string | null,
+  mergeSubModels: boolean = false
 ): Promise<{
   motions: string[];
   expressions: string[];
+  motionGroups: MotionGroup[];
+  motionSources: NameSource[];
+  expressionSources: NameSource[];
+  failedSubModels: string[];
 }> {
   try {
     const { invoke } = await import('@tauri-apps/api/core');
@@ -47,7 +66,11 @@ export async function extractMotionsAndExpressions(
             console.log(`✅ [Mano] 成功从前端提取: ${poses.length} 个 poses`);
             return {
               motions: poses, // 将 Mano 的 poses 映射到 motions
-              expressions: poses // 同时映射到 expressions，让两个下拉框都能选
+              expressions: poses, // 同时映射到 expressions，让两个下拉框都能选
+              motionGroups: [],
+              motionSources: [],
+              expressionSources: [],
+              failedSubModels: []
             };
           }
         }
@@ -57,18 +80,30 @@ export async function extractMotionsAndExpressions(
     }
 
     // 调用后端命令
-    const result = await invoke<{ motions: string[]; expressions: string[] }>(
+    const result = await invoke<{
+      motions: string[];
+      expressions: string[];
+      motionGroups: MotionGroup[];
+      motionSources: NameSource[];
+      expressionSources: NameSource[];
+      failedSubModels: string[];
+    }>(
       'extract_jsonl_motions_expressions',
       {
         filePath: filePath,
-        gameFolder: finalGameFolder || null
+        gameFolder: finalGameFolder || null,
+        mergeSubModels
       }
     );
     
     console.log(`✅ 成功提取: ${result.motions.length} 个 motions, ${result.expressions.length} 个 expressions`);
     return {
       motions: result.motions || [],
-      expressions: result.expressions || []
+      expressions: result.expressions || [],
+      motionGroups: result.motionGroups || [],
+      motionSources: result.motionSources || [],
+      expressionSources: result.expressionSources || [],
+      failedSubModels: result.failedSubModels || []
     };
   } catch (error) {
     console.error('❌ 提取 motions 和 expressions 失败:', error);
@@ -76,7 +111,46 @@ export async function extractMotionsAndExpressions(
     if (error instanceof Error) {
       console.error('   错误信息:', error.message);
     }
-    return { motions: [], expressions: [] };
+    return { motions: [], expressions: [], motionGroups: [], motionSources: [], expressionSources: [], failedSubModels: [] };
   }
 }
 
+/** 动作文件的时间信息，时间单位为秒 */
+export interface MotionTiming {
+  group: string;
+  index: number;
+  /** 相对于模型文件所在目录的路径 */
+  file: string;
+  /** 动作来自 JSONL 的哪个子模型 */
+  subModel?: string;
+  info: {
+    format: 'mtn' | 'motion3';
+    duration: number;
+    fps: number;
+    loop: boolean | null;
+    fadeIn: number | null;
+    fadeOut: number | null;
+    curves: { target: string; id: string }[];
+  } | null;
+  error?: string;
+}
+
+/**
+ * 读取模型中每个动作（.mtn / .motion3.json）的时长、帧率、循环和淡入淡出，用于在时间轴上显示动作长度
+ * @param filePath 模型文件路径（相对于游戏文件夹的 figure 目录）
+ */
+export async function getMotionTimings(
+  filePath: string,
+  gameFolder?: string | null
+): Promise<MotionTiming[]> {
+  try {
+    const { invoke } = await import('@tauri-apps/api/core');
+    return await invoke<MotionTiming[]>('get_motion_timings', {
+      filePath,
+      gameFolder: gameFolder || null
+    });
+  } catch (error) {
+    console.error('❌ 读取动作时间信息失败:', error);
+    return [];
+  }
+}
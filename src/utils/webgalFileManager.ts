@@ -1,13 +1,63 @@
 import { open } from '@tauri-apps/plugin-dialog';
 import { readFile } from '@tauri-apps/plugin-fs';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { getMimeType } from './fileTypeDetector';
 
+/** 后端扫描得到的素材类型 */
+export type ScannedAssetKind = 'image' | 'gif' | 'webm' | 'live2d_json' | 'cubism3' | 'jsonl' | 'mano';
+
+/** 模型依赖树中的一个文件 */
+export interface ModelReference {
+    kind: 'moc' | 'texture' | 'physics' | 'pose' | 'display_info' | 'user_data' | 'motion' | 'sound' | 'expression' | 'sub_model' | 'layer';
+    path: string;
+    /** 动作所在的分组，或表情的名称 */
+    name?: string;
+    index?: number;
+    /** 子模型引用的文件 */
+    children?: ModelReference[];
+}
+
+/** scan_directory_detailed 返回的素材信息 */
+export interface ScannedAsset {
+    path: string;
+    kind: ScannedAssetKind;
+    size: number;
+    /** Unix 毫秒 */
+    modified: number | null;
+    width: number | null;
+    height: number | null;
+    /** 被归入该素材的贴图、子模型等文件 */
+    dependencies: string[];
+    /** 模型引用的文件，保留清单中的结构，包含不存在的文件 */
+    dependencyTree: ModelReference[];
+}
+
+/** validate_figure 返回的问题，路径相对于被校验文件所在目录 */
+export type FigureIssue =
+    | { type: 'missing_file'; path: string; kind: ModelReference['kind']; referencedBy: string }
+    | { type: 'unreadable_file'; path: string; error: string }
+    | { type: 'malformed_json'; path: string; line: number | null; error: string }
+    | { type: 'case_mismatch'; path: string; actualPath: string; kind: ModelReference['kind']; referencedBy: string }
+    | { type: 'invalid_sub_model'; path: string; referencedBy: string }
+    | { type: 'unreachable_motion'; group: string; index: number | null; referencedBy: string; reason: 'no_file' | 'not_in_any_sub_model' }
+    | { type: 'unreachable_expression'; name: string; index: number | null; referencedBy: string; reason: 'no_file' | 'not_in_any_sub_model' };
+
+export interface FigureReport {
+    kind: ScannedAssetKind | null;
+    valid: boolean;
+    issues: FigureIssue[];
+}
+
 export class WebGALFileManager {
     private gameFolder: string | null = null;
     private figureFiles: string[] = [];
     private backgroundFiles: string[] = [];
     private fileServerBaseUrl: string | null = null;
+    /** 正在进行的后台扫描，切换文件夹时取消 */
+    private activeScanIds: string[] = [];
+    /** assets-changed 事件的监听，首次扫描完成后注册 */
+    private assetsChangedUnlisten: UnlistenFn | null = null;
 
     async selectGameFolder(): Promise<string | null> {
         try {
@@ -35,9 +85,9 @@ export class WebGALFileManager {
         
         // 启动本地文件服务器
         try {
-            const serverUrl = await invoke<string>('start_local_server', { basePath: folderPath });
-            this.fileServerBaseUrl = serverUrl;
-            console.log('✅ 本地文件服务器已启动:', serverUrl);
+            const serverInfo = await invoke<{ baseUrl: string; token: string | null }>('start_local_server', { basePath: folderPath });
+            this.fileServerBaseUrl = serverInfo.baseUrl;
+            console.log('✅ 本地文件服务器已启动');
         } catch (error) {
             console.error('启动文件服务器失败:', error);
         }
@@ -48,6 +98,12 @@ export class WebGALFileManager {
     private async scanFiles(): Promise<void> {
         if (!this.gameFolder) return;
 
+        // 切换文件夹时取消上一个文件夹尚未完成的扫描
+        await Promise.all(this.activeScanIds.map(scanId => invoke('cancel_directory_scan', { scanId }).catch(() => false)));
+        const figureScanId = `figure-${Date.now()}`;
+        const backgroundScanId = `background-${Date.now()}`;
+        this.activeScanIds = [figureScanId, backgroundScanId];
+
         try {
             const figurePath = `${this.gameFolder}/game/figure`;
             const backgroundPath = `${this.gameFolder}/game/background`;
@@ -55,20 +111,27 @@ export class WebGALFileManager {
             console.log('正在递归扫描立绘文件夹:', figurePath);
             console.log('正在递归扫描背景文件夹:', backgroundPath);
 
-            // 使用 Rust 后端进行递归扫描
+            // 使用 Rust 后端进行递归扫描；扫描期间切换了文件夹时丢弃过期的结果
+            const isCurrent = (scanId: string) => this.activeScanIds.includes(scanId);
             try {
-                this.figureFiles = await invoke('scan_directory_recursive', { dirPath: figurePath }) as string[];
+                const figureFiles = await invoke<string[]>('scan_directory_recursive', { dirPath: figurePath, scanId: figureScanId });
+                if (!isCurrent(figureScanId)) return;
+                this.figureFiles = figureFiles;
                 console.log(`✅ 找到 ${this.figureFiles.length} 个立绘文件`);
                 console.log('📋 前 5 个文件路径:', this.figureFiles.slice(0, 5));
             } catch (error) {
+                if (!isCurrent(figureScanId)) return;
                 console.warn('无法读取立绘文件夹:', error);
                 this.figureFiles = [];
             }
 
             try {
-                this.backgroundFiles = await invoke('scan_directory_recursive', { dirPath: backgroundPath }) as string[];
+                const backgroundFiles = await invoke<string[]>('scan_directory_recursive', { dirPath: backgroundPath, scanId: backgroundScanId });
+                if (!isCurrent(backgroundScanId)) return;
+                this.backgroundFiles = backgroundFiles;
                 console.log(`✅ 找到 ${this.backgroundFiles.length} 个背景文件`);
             } catch (error) {
+                if (!isCurrent(backgroundScanId)) return;
                 console.warn('无法读取背景文件夹:', error);
                 this.backgroundFiles = [];
             }
@@ -77,6 +140,38 @@ export class WebGALFileManager {
             this.figureFiles = [];
             this.backgroundFiles = [];
         }
+
+        await this.watchAssetFolders();
+    }
+
+    /**
+     * 由后端监视立绘和背景文件夹，文件增删后按相同的归入规则更新列表
+     */
+    private async watchAssetFolders(): Promise<void> {
+        if (!this.gameFolder) return;
+
+        if (!this.assetsChangedUnlisten) {
+            this.assetsChangedUnlisten = await listen<{ folder: 'figure' | 'background'; added: string[]; removed: string[] }>(
+                'assets-changed',
+                (event) => {
+                    const { folder, added, removed } = event.payload;
+                    const files = folder === 'figure' ? this.figureFiles : this.backgroundFiles;
+                    const updated = files.filter(f => !removed.includes(f)).concat(added.filter(f => !files.includes(f)));
+                    if (folder === 'figure') {
+                        this.figureFiles = updated;
+                    } else {
+                        this.backgroundFiles = updated;
+                    }
+                    console.log(`🔄 ${folder} 素材变化: +${added.length} -${removed.length}`);
+                }
+            );
+        }
+
+        try {
+            await invoke('watch_game_assets', { gameFolder: this.gameFolder });
+        } catch (error) {
+            console.warn('无法监视素材文件夹:', error);
+        }
     }
 
 
@@ -135,6 +230,47 @@ export class WebGALFileManager {
         return await this.getImageAsBlobUrl('background', found);
     }
 
+    /**
+     * 获取立绘/背景的缩略图 URL，由本地文件服务器按需缩放并缓存
+     */
+    getThumbnailUrl(type: 'figure' | 'background', filename: string, width: number = 256): string | null {
+        if (!this.fileServerBaseUrl) return null;
+        const encodedPath = filename.split('/').map(encodeURIComponent).join('/');
+        return `${this.fileServerBaseUrl}/__thumb/game/${type}/${encodedPath}?w=${width}`;
+    }
+
+    /**
+     * 获取立绘/背景的详细信息（类型、大小、尺寸、依赖文件），用于在选择器中显示
+     */
+    async scanAssetsDetailed(type: 'figure' | 'background'): Promise<ScannedAsset[]> {
+        if (!this.gameFolder) return [];
+        return await invoke<ScannedAsset[]>('scan_directory_detailed', {
+            dirPath: `${this.gameFolder}/game/${type}`
+        });
+    }
+
+    /**
+     * 校验立绘引用的贴图、动作、子模型和图层是否存在，以及路径大小写是否一致
+     */
+    async validateFigure(filename: string): Promise<FigureReport | null> {
+        if (!this.gameFolder) return null;
+        return await invoke<FigureReport>('validate_figure', {
+            path: `${this.gameFolder}/game/figure/${filename}`
+        });
+    }
+
+    /**
+     * 监听游戏文件夹中的文件变化，路径相对于游戏文件夹，如 `game/figure/a.png`
+     */
+    async onServedFilesChanged(
+        callback: (changes: { changed: string[]; added: string[]; removed: string[] }) => void
+    ): Promise<UnlistenFn> {
+        return await listen<{ changed: string[]; added: string[]; removed: string[] }>(
+            'file-server-changed',
+            (event) => callback(event.payload)
+        );
+    }
+
     private async getImageAsBlobUrl(type: 'figure' | 'background', filename: string): Promise<string | null> {
         try {
             const folderPath = type === 'figure' ? 'figure' : 'background';